@@ -0,0 +1,328 @@
+//! Output formats for selected parts of records.
+//! The char, byte and field selections can all be written as JSON Lines.
+//!
+
+use std::borrow::Cow;
+
+use rtools_traits::{RecordContext, StatefulProcessorT};
+
+use crate::{
+    select_bytes, select_chars_utf8, select_numbered_fields_utf8,
+    select_numbered_fields_with_missing_utf8, ByteLineProcessor,
+    transform_field, CharContextT, CharUtf8LineProcessor, FieldContextT, FieldUtf8LineProcessor,
+};
+
+/// Part of a record selected for output
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Part<'a> {
+    /// Field number, char/byte range or key path the part was selected by, e.g. `3`, `2-5` or `user.id`
+    pub label: String,
+    pub text: Cow<'a, str>,
+}
+
+/// Selection of labelled parts from a line
+pub trait SelectT<C> {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>>;
+}
+
+/// Label of the range, e.g. `3`, `2-5` or `7-` for an open range
+pub fn range_label(ranged_pair: &(usize, usize)) -> String {
+    match ranged_pair {
+        (start_pos, end_pos) if start_pos == end_pos => start_pos.to_string(),
+        (start_pos, usize::MAX) => format!("{}-", start_pos),
+        (start_pos, end_pos) => format!("{}-{}", start_pos, end_pos),
+    }
+}
+
+impl<C: CharContextT> SelectT<C> for CharUtf8LineProcessor {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        // Ranges past the end of the line select nothing, labels follow the remaining ranges
+        let ranged_pairs = context
+            .ranged_pairs()
+            .iter()
+            .filter(|(start_pos, _)| *start_pos <= line.chars().count());
+
+        ranged_pairs
+            .zip(select_chars_utf8(line, context.ranged_pairs()))
+            .map(|(ranged_pair, text)| Part {
+                label: range_label(ranged_pair),
+                text: Cow::Borrowed(text),
+            })
+            .collect()
+    }
+}
+
+impl<C: CharContextT> SelectT<C> for ByteLineProcessor {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        // Multi-byte characters split by the ranges are replaced by U+FFFD
+        context
+            .ranged_pairs()
+            .iter()
+            .zip(select_bytes(line, context.ranged_pairs()))
+            .map(|(ranged_pair, bytes)| Part {
+                label: range_label(ranged_pair),
+                text: String::from_utf8_lossy(bytes),
+            })
+            .collect()
+    }
+}
+
+impl<C: FieldContextT> SelectT<C> for FieldUtf8LineProcessor {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        let fill = match context.fill_missing() {
+            Some(fill) => fill,
+            None => {
+                return select_numbered_fields_utf8(line, context.ranged_pairs(), context.delim())
+                    .into_iter()
+                    .map(|(field_number, text)| Part {
+                        label: field_number.to_string(),
+                        text: transform_field(field_number, text, context.transforms()),
+                    })
+                    .collect();
+            }
+        };
+
+        select_numbered_fields_with_missing_utf8(line, context.ranged_pairs(), context.delim())
+            .into_iter()
+            .map(|(field_number, text)| Part {
+                label: field_number.to_string(),
+                text: match text {
+                    Some(text) => transform_field(field_number, text, context.transforms()),
+                    // The fill value is borrowed from the context, not the line
+                    None => Cow::Owned(transform_field(field_number, fill, context.transforms()).into_owned()),
+                },
+            })
+            .collect()
+    }
+}
+
+/// Append the text to the output as a JSON string literal
+pub fn write_json_string(text: &str, out: &mut String) {
+    out.push('"');
+
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            '\u{08}' => out.push_str("\\b"),
+            '\u{0c}' => out.push_str("\\f"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// Keys of the JSON objects written for records
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum JsonKeys {
+    /// Write JSON arrays instead of objects
+    None,
+    /// Key parts by their labels: field numbers, char/byte ranges or key paths
+    Numbers,
+    /// Key parts by the parts selected from the first processed line of each file.
+    /// That line is consumed as the header
+    Header,
+}
+
+/// Stateful processor writing the selected parts of each record as a line of JSON.
+/// Drive it with a `StatefulLineProcessor`
+pub struct JsonLineProcessor<S> {
+    selector: S,
+    keys: JsonKeys,
+    // Header of the current file, until its first record is processed
+    header: Option<Vec<String>>,
+}
+
+impl<S> JsonLineProcessor<S> {
+    pub fn new(selector: S, keys: JsonKeys) -> JsonLineProcessor<S> {
+        JsonLineProcessor {
+            selector,
+            keys,
+            header: None,
+        }
+    }
+}
+
+/// Format the parts as a JSON array or an object keyed by the keys.
+/// Parts without a key are keyed by their labels
+pub fn format_json(parts: &[Part], keys: Option<&[String]>) -> String {
+    let mut out = String::new();
+
+    match keys {
+        None => {
+            out.push('[');
+            for (index, part) in parts.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_json_string(&part.text, &mut out);
+            }
+            out.push(']');
+        }
+        Some(keys) => {
+            out.push('{');
+            for (index, part) in parts.iter().enumerate() {
+                if index > 0 {
+                    out.push(',');
+                }
+                write_json_string(keys.get(index).unwrap_or(&part.label), &mut out);
+                out.push(':');
+                write_json_string(&part.text, &mut out);
+            }
+            out.push('}');
+        }
+    }
+
+    out.push('\n');
+    out
+}
+
+impl<C, S: SelectT<C>> StatefulProcessorT<C> for JsonLineProcessor<S> {
+    /// Each file has its own header
+    fn begin_file(&mut self, _file: &str, _context: &C) -> Vec<u8> {
+        self.header = None;
+        vec![]
+    }
+
+    fn process_line(&mut self, line: &str, _record: &RecordContext, context: &C) -> Vec<u8> {
+        let parts = self.selector.select_parts(line, context);
+
+        match self.keys {
+            JsonKeys::None => format_json(&parts, None).into_bytes(),
+            JsonKeys::Numbers => format_json(&parts, Some(&[])).into_bytes(),
+            JsonKeys::Header => match &self.header {
+                Some(header) => format_json(&parts, Some(header)).into_bytes(),
+                None => {
+                    self.header = Some(parts.into_iter().map(|part| part.text.into_owned()).collect());
+                    vec![]
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{CharContext, FieldContext, FieldProcessor};
+    use rtools_traits::{LineProcessorT, RecordFilter, RtoolOptions, RtoolT, StatefulLineProcessor};
+    use std::io::{BufWriter, Cursor};
+
+    fn json_string(text: &str) -> String {
+        let mut out = String::new();
+        write_json_string(text, &mut out);
+        out
+    }
+
+    #[test]
+    fn test_write_json_string() {
+        assert_eq!(r#""plain 🐣""#, json_string("plain 🐣"));
+        assert_eq!(r#""a\"b\\c""#, json_string("a\"b\\c"));
+        assert_eq!(r#""\t\r\n\b\f\u0001\u001f""#, json_string("\t\r\n\u{8}\u{c}\u{1}\u{1f}"));
+    }
+
+    #[test]
+    fn test_range_label() {
+        assert_eq!("3", range_label(&(3, 3)));
+        assert_eq!("2-5", range_label(&(2, 5)));
+        assert_eq!("7-", range_label(&(7, usize::MAX)));
+    }
+
+    #[test]
+    fn test_json_fields() {
+        let ranged_pairs = vec![(1, 1), (3, 4)];
+        let context = FieldContext::new(&ranged_pairs, ",");
+        let record = RecordContext {
+            line_number: 2,
+            ..Default::default()
+        };
+
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, JsonKeys::None));
+        assert_eq!(
+            "[\"a\",\"c \\\"q\\\"\",\"d\"]\n".as_bytes(),
+            &processor.process_record("a,b,c \"q\",d,e", &record, &context)[..]
+        );
+
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, JsonKeys::Numbers));
+        assert_eq!(
+            "{\"1\":\"a\",\"3\":\"c\"}\n".as_bytes(),
+            &processor.process_record("a,b,c", &record, &context)[..]
+        );
+    }
+
+    #[test]
+    fn test_json_fields_with_missing() {
+        let ranged_pairs = vec![(1, 1), (3, 4)];
+        let context = FieldContext::new(&ranged_pairs, ",").with_fill_missing("-");
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, JsonKeys::Numbers));
+
+        assert_eq!(
+            "{\"1\":\"a\",\"3\":\"c\",\"4\":\"-\"}\n".as_bytes(),
+            &processor.process("a,b,c", &context)[..]
+        );
+    }
+
+    #[test]
+    fn test_json_fields_keyed_by_header() {
+        let ranged_pairs = vec![(1, 1), (3, 3)];
+        let context = FieldContext::new(&ranged_pairs, ",");
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, JsonKeys::Header));
+        let record = |line_number| RecordContext {
+            line_number,
+            ..Default::default()
+        };
+
+        assert!(processor.process_record("id,x,name", &record(1), &context).is_empty());
+        assert_eq!(
+            "{\"id\":\"1\",\"name\":\"🐣\"}\n".as_bytes(),
+            &processor.process_record("1,_,🐣", &record(2), &context)[..]
+        );
+    }
+
+    #[test]
+    fn test_json_fields_keyed_by_header_of_each_file() {
+        let ranged_pairs = vec![(1, 1), (2, 2)];
+        let context = FieldContext::new(&ranged_pairs, ",");
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, JsonKeys::Header));
+        // The header is the first processed line, it is not annotated
+        let options = RtoolOptions {
+            line_numbers: true,
+            filter: RecordFilter {
+                skip_lines: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let mut out = BufWriter::new(vec![]);
+        for input in ["# comment\nid,name\n1,ann\n", "# comment\nkey,value\nk,v\n"] {
+            FieldProcessor {}
+                .process_input(&processor, "a.csv", Cursor::new(input), &mut out, &context, &options)
+                .unwrap();
+        }
+        assert_eq!(
+            "3:{\"id\":\"1\",\"name\":\"ann\"}\n3:{\"key\":\"k\",\"value\":\"v\"}\n",
+            String::from_utf8(out.into_inner().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_json_chars_and_bytes() {
+        let ranged_pairs = vec![(1, 2), (4, usize::MAX), (9, 9)];
+        let context = CharContext::new(&ranged_pairs);
+
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(CharUtf8LineProcessor {}, JsonKeys::Numbers));
+        assert_eq!(
+            "{\"1-2\":\"🦃🐔\",\"4-\":\"🐣🐤\"}\n".as_bytes(),
+            &processor.process("🦃🐔🐓🐣🐤", &context)[..]
+        );
+
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(ByteLineProcessor {}, JsonKeys::None));
+        assert_eq!("[\"ab\",\"de\"]\n".as_bytes(), &processor.process("abcde", &context)[..]);
+    }
+}
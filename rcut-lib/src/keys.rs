@@ -0,0 +1,226 @@
+//! Selection of values from JSON Lines by key paths, e.g. `user.id,status`.
+//! Each line is parsed as a JSON value and the selected values are written
+//! like fields: joined by a delimiter, missing keys yield empty fields.
+//!
+
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use rtools_traits::{LineProcessorT, RtoolT};
+
+#[cfg(feature = "async")]
+use rtools_traits::AsyncRtoolT;
+
+use crate::format::{Part, SelectT};
+
+/// Path of keys into nested objects. Numeric keys also index into arrays
+pub type KeyPath = Vec<String>;
+
+/// Extract list of comma-separated key paths with dot-separated keys
+pub fn extract_key_paths(key_paths_str: &str) -> Vec<KeyPath> {
+    key_paths_str
+        .split(',')
+        .map(|key_path| key_path.split('.').map(String::from).collect())
+        .collect()
+}
+
+pub trait KeyContextT {
+    fn key_paths(&self) -> &Vec<KeyPath>;
+
+    fn delim(&self) -> &str;
+}
+
+pub struct KeyContext<'a> {
+    key_paths: &'a Vec<KeyPath>,
+    delim: &'a str,
+}
+
+impl<'a> KeyContext<'a> {
+    pub fn new(key_paths: &'a Vec<KeyPath>, delim: &'a str) -> KeyContext<'a> {
+        KeyContext { key_paths, delim }
+    }
+}
+
+impl KeyContextT for KeyContext<'_> {
+    fn key_paths(&self) -> &Vec<KeyPath> {
+        self.key_paths
+    }
+
+    fn delim(&self) -> &str {
+        self.delim
+    }
+}
+
+/// Look up the key path in the value
+fn lookup<'v>(value: &'v Value, key_path: &[String]) -> Option<&'v Value> {
+    key_path.iter().try_fold(value, |value, key| match value {
+        Value::Object(map) => map.get(key),
+        Value::Array(items) => key.parse::<usize>().ok().and_then(|index| items.get(index)),
+        _ => None,
+    })
+}
+
+/// Text of the value as written to fields: Strings without quotes,
+/// nulls as empty fields, objects and arrays as compact JSON
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+/// Escape the value with backslashes so that it stays one field on one line:
+/// Backslashes, TABs, line breaks and the delimiter are escaped
+pub fn escape_value<'a>(text: &'a str, delim: &str) -> Cow<'a, str> {
+    const ESCAPED: [char; 4] = ['\\', '\t', '\n', '\r'];
+    // Delimiters starting with an escaped char are escaped along with it
+    let delim = if delim.is_empty() || delim.starts_with(ESCAPED) { None } else { Some(delim) };
+
+    if !text.contains(ESCAPED) && delim.is_none_or(|delim| !text.contains(delim)) {
+        return Cow::Borrowed(text);
+    }
+
+    let mut escaped = String::with_capacity(text.len() + 8);
+    let mut rest = text;
+    while let Some(c) = rest.chars().next() {
+        if let Some(delim) = delim.filter(|delim| rest.starts_with(delim)) {
+            escaped.push('\\');
+            escaped.push_str(delim);
+            rest = &rest[delim.len()..];
+            continue;
+        }
+
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\t' => escaped.push_str("\\t"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            c => escaped.push(c),
+        }
+        rest = &rest[c.len_utf8()..];
+    }
+
+    Cow::Owned(escaped)
+}
+
+/// Join the values by the delimiter into a line, escaping them with `escape_value`
+pub fn join_values<S: AsRef<str>>(values: &[S], delim: &str) -> Vec<u8> {
+    let mut out_bytes: Vec<u8> = vec![];
+
+    for (index, value) in values.iter().enumerate() {
+        if index > 0 {
+            out_bytes.extend(delim.as_bytes());
+        }
+        out_bytes.extend(escape_value(value.as_ref(), delim).as_bytes());
+    }
+
+    out_bytes.push(b'\n');
+    out_bytes
+}
+
+/// Select values from a line of JSON by key paths.
+/// Missing keys and lines that are not JSON yield empty values
+pub fn select_values_json(line: &str, key_paths: &[KeyPath]) -> Vec<String> {
+    let value: Value = serde_json::from_str(line).unwrap_or(Value::Null);
+
+    key_paths
+        .iter()
+        .map(|key_path| lookup(&value, key_path).map(value_text).unwrap_or_default())
+        .collect()
+}
+
+pub struct KeyJsonLineProcessor {}
+
+impl<C: KeyContextT> LineProcessorT<C> for KeyJsonLineProcessor {
+    /// Extract values of a JSON encoded line
+    fn process(&self, line: &str, context: &C) -> Vec<u8> {
+        join_values(&select_values_json(line, context.key_paths()), context.delim())
+    }
+}
+
+impl<C: KeyContextT> SelectT<C> for KeyJsonLineProcessor {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        context
+            .key_paths()
+            .iter()
+            .zip(select_values_json(line, context.key_paths()))
+            .map(|(key_path, text)| Part {
+                label: key_path.join("."),
+                text: Cow::Owned(text),
+            })
+            .collect()
+    }
+}
+
+pub struct KeyProcessor {}
+
+impl<C: KeyContextT, P: LineProcessorT<C>> RtoolT<C, P> for KeyProcessor {}
+
+#[cfg(feature = "async")]
+impl<C: KeyContextT, P: LineProcessorT<C>> AsyncRtoolT<C, P> for KeyProcessor {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::format::{JsonKeys, JsonLineProcessor};
+    use rtools_traits::StatefulLineProcessor;
+
+    #[test]
+    fn test_extract_key_paths() {
+        assert_eq!(
+            vec![vec!["user", "id"], vec!["status"]],
+            extract_key_paths("user.id,status")
+        );
+    }
+
+    #[test]
+    fn test_select_values_json() {
+        let key_paths = extract_key_paths("user.id,status,tags.1,user,missing,ok,note");
+        let line = r#"{"user":{"id":7},"status":"ok \"🐣\"","tags":["a","b"],"ok":true,"note":null}"#;
+
+        assert_eq!(
+            vec!["7", "ok \"🐣\"", "b", r#"{"id":7}"#, "", "true", ""],
+            select_values_json(line, &key_paths)
+        );
+        assert_eq!(vec![""; 7], select_values_json("not json", &key_paths));
+    }
+
+    #[test]
+    fn test_escape_value() {
+        assert_eq!("plain", escape_value("plain", "\t"));
+        assert_eq!("a\\tb\\nc\\\\d\\r", escape_value("a\tb\nc\\d\r", "\t"));
+        assert_eq!("a\\,b | c", escape_value("a,b | c", ","));
+        assert_eq!("a\\ | b,c", escape_value("a | b,c", " | "));
+        assert_eq!("a,b", escape_value("a,b", ""));
+    }
+
+    #[test]
+    fn test_process_keys_escapes_values() {
+        let key_paths = extract_key_paths("a,b");
+        let context = KeyContext::new(&key_paths, ",");
+
+        assert_eq!(
+            "x\\ny\\,z,\\t\n".as_bytes(),
+            &KeyJsonLineProcessor {}.process(r#"{"a":"x\ny,z","b":"\t"}"#, &context)[..]
+        );
+    }
+
+    #[test]
+    fn test_process_keys() {
+        let key_paths = extract_key_paths("user.id,status,level");
+        let context = KeyContext::new(&key_paths, "\t");
+
+        assert_eq!(
+            "7\t200\t\n".as_bytes(),
+            &KeyJsonLineProcessor {}.process(r#"{"user":{"id":"7"},"status":200}"#, &context)[..]
+        );
+
+        let processor = StatefulLineProcessor::new(JsonLineProcessor::new(KeyJsonLineProcessor {}, JsonKeys::Numbers));
+        assert_eq!(
+            "{\"user.id\":\"7\",\"status\":\"200\",\"level\":\"\"}\n".as_bytes(),
+            &processor.process(r#"{"user":{"id":7},"status":200}"#, &context)[..]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+//! Fixed-width layouts: named columns mapped to char or byte ranges,
+//! e.g. `name:1-20,amount:21-32`.
+//!
+
+use std::borrow::Cow;
+
+use rtools_traits::{LineProcessorT, RtoolT};
+
+#[cfg(feature = "async")]
+use rtools_traits::AsyncRtoolT;
+
+use crate::format::{Part, SelectT};
+use crate::CharContext;
+
+/// Named column of a fixed-width layout
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Column {
+    pub name: String,
+    /// Char or byte range of the column, see `str_to_ranged_pair`
+    pub ranged_pair: (usize, usize),
+}
+
+/// Parse the layout of columns `name:range` separated by commas or newlines.
+/// Blank lines and lines starting with `#` are ignored, so layouts can be kept in files
+pub fn parse_layout(layout_str: &str) -> Result<Vec<Column>, String> {
+    let mut columns = vec![];
+
+    for line in layout_str.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        for column_str in line.split(',').map(str::trim) {
+            let (name, range) = match column_str.split_once(':') {
+                Some((name, range)) if !name.trim().is_empty() => (name.trim(), range.trim()),
+                _ => return Err(format!("invalid column `{}`, expected name:range", column_str)),
+            };
+
+            columns.push(Column {
+                name: name.to_string(),
+                ranged_pair: parse_range(range)
+                    .ok_or_else(|| format!("invalid range `{}` of column `{}`", range, name))?,
+            });
+        }
+    }
+
+    if columns.is_empty() {
+        return Err("the layout has no columns".to_string());
+    }
+
+    Ok(columns)
+}
+
+/// Parse the range having the pattern `(\d+|\d+-|-\d+|\d+-\d+)` with positions from 1
+fn parse_range(range: &str) -> Option<(usize, usize)> {
+    let parse_pos = |pos: &str, default: usize| {
+        if pos.is_empty() {
+            Some(default)
+        } else {
+            pos.parse::<usize>().ok().filter(|pos| *pos > 0)
+        }
+    };
+
+    let (start_pos, end_pos) = match range.split_once('-') {
+        Some((start_pos, end_pos)) if range != "-" => {
+            (parse_pos(start_pos, 1)?, parse_pos(end_pos, usize::MAX)?)
+        }
+        Some(_) => return None,
+        None => {
+            let pos = parse_pos(range, 0).filter(|pos| *pos > 0)?;
+            (pos, pos)
+        }
+    };
+
+    if start_pos <= end_pos {
+        Some((start_pos, end_pos))
+    } else {
+        None
+    }
+}
+
+/// Select the columns of the layout by their comma-separated names, in the given order
+pub fn select_columns(layout: &[Column], names_str: &str) -> Result<Vec<Column>, String> {
+    names_str
+        .split(',')
+        .map(str::trim)
+        .map(|name| {
+            layout
+                .iter()
+                .find(|column| column.name == name)
+                .cloned()
+                .ok_or_else(|| format!("unknown column `{}`", name))
+        })
+        .collect()
+}
+
+pub trait LayoutContextT {
+    fn columns(&self) -> &Vec<Column>;
+
+    fn delim(&self) -> &str;
+
+    /// Whether to trim the padding around values
+    fn trim(&self) -> bool;
+}
+
+pub struct LayoutContext<'a> {
+    columns: &'a Vec<Column>,
+    delim: &'a str,
+    trim: bool,
+}
+
+impl<'a> LayoutContext<'a> {
+    pub fn new(columns: &'a Vec<Column>, delim: &'a str, trim: bool) -> LayoutContext<'a> {
+        LayoutContext {
+            columns,
+            delim,
+            trim,
+        }
+    }
+}
+
+impl LayoutContextT for LayoutContext<'_> {
+    fn columns(&self) -> &Vec<Column> {
+        self.columns
+    }
+
+    fn delim(&self) -> &str {
+        self.delim
+    }
+
+    fn trim(&self) -> bool {
+        self.trim
+    }
+}
+
+/// Line processor cutting the columns of a layout with the char or byte selector,
+/// e.g. `CharUtf8LineProcessor` or `ByteLineProcessor`
+pub struct LayoutLineProcessor<S> {
+    selector: S,
+}
+
+impl<S> LayoutLineProcessor<S> {
+    pub fn new(selector: S) -> LayoutLineProcessor<S> {
+        LayoutLineProcessor { selector }
+    }
+}
+
+impl<S: for<'c> SelectT<CharContext<'c>>> LayoutLineProcessor<S> {
+    /// Values of the columns. Columns past the end of the line are empty
+    fn select_values<'a, C: LayoutContextT>(&self, line: &'a str, context: &C) -> Vec<Cow<'a, str>> {
+        context
+            .columns()
+            .iter()
+            .map(|column| {
+                let ranged_pairs = vec![column.ranged_pair];
+                let value = self
+                    .selector
+                    .select_parts(line, &CharContext::new(&ranged_pairs))
+                    .pop()
+                    .map_or(Cow::Borrowed(""), |part| part.text);
+
+                match value {
+                    Cow::Borrowed(value) if context.trim() => Cow::Borrowed(value.trim()),
+                    Cow::Owned(value) if context.trim() => Cow::Owned(value.trim().to_string()),
+                    value => value,
+                }
+            })
+            .collect()
+    }
+}
+
+impl<C: LayoutContextT, S: for<'c> SelectT<CharContext<'c>>> LineProcessorT<C> for LayoutLineProcessor<S> {
+    /// Extract columns of a fixed-width line
+    fn process(&self, line: &str, context: &C) -> Vec<u8> {
+        let mut out_bytes = self
+            .select_values(line, context)
+            .join(context.delim())
+            .into_bytes();
+        out_bytes.push(b'\n');
+        out_bytes
+    }
+}
+
+impl<C: LayoutContextT, S: for<'c> SelectT<CharContext<'c>>> SelectT<C> for LayoutLineProcessor<S> {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        context
+            .columns()
+            .iter()
+            .zip(self.select_values(line, context))
+            .map(|(column, text)| Part {
+                label: column.name.clone(),
+                text,
+            })
+            .collect()
+    }
+}
+
+pub struct LayoutProcessor {}
+
+impl<C: LayoutContextT, P: LineProcessorT<C>> RtoolT<C, P> for LayoutProcessor {}
+
+#[cfg(feature = "async")]
+impl<C: LayoutContextT, P: LineProcessorT<C>> AsyncRtoolT<C, P> for LayoutProcessor {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ByteLineProcessor, CharUtf8LineProcessor};
+
+    fn column(name: &str, ranged_pair: (usize, usize)) -> Column {
+        Column {
+            name: name.to_string(),
+            ranged_pair,
+        }
+    }
+
+    #[test]
+    fn test_parse_layout() {
+        assert_eq!(
+            Ok(vec![column("name", (1, 20)), column("amount", (21, 32))]),
+            parse_layout("name:1-20, amount:21-32")
+        );
+        assert_eq!(
+            Ok(vec![
+                column("id", (1, 4)),
+                column("flag", (5, 5)),
+                column("rest", (6, usize::MAX)),
+            ]),
+            parse_layout("# Layout of the extract\nid:-4\n\nflag:5,rest:6-\n")
+        );
+    }
+
+    #[test]
+    fn test_parse_layout_errors() {
+        assert!(parse_layout("").is_err());
+        assert!(parse_layout("name").is_err());
+        assert!(parse_layout(":1-2").is_err());
+        assert!(parse_layout("name:-").is_err());
+        assert!(parse_layout("name:0").is_err());
+        assert!(parse_layout("name:5-3").is_err());
+        assert!(parse_layout("name:a-3").is_err());
+    }
+
+    #[test]
+    fn test_select_columns() {
+        let layout = parse_layout("a:1-2,b:3-4,c:5-6").unwrap();
+        assert_eq!(
+            Ok(vec![column("c", (5, 6)), column("a", (1, 2))]),
+            select_columns(&layout, "c,a")
+        );
+        assert!(select_columns(&layout, "a,x").is_err());
+    }
+
+    #[test]
+    fn test_process_layout() {
+        let columns = parse_layout("name:1-6,amount:7-12,note:13-").unwrap();
+        let line = "Bob   000042";
+
+        let context = LayoutContext::new(&columns, ",", false);
+        let line_processor = LayoutLineProcessor::new(CharUtf8LineProcessor {});
+        assert_eq!("Bob   ,000042,\n".as_bytes(), &line_processor.process(line, &context)[..]);
+
+        let context = LayoutContext::new(&columns, "|", true);
+        assert_eq!("🐣|12|\n".as_bytes(), &line_processor.process("🐣     12    ", &context)[..]);
+
+        let line_processor = LayoutLineProcessor::new(ByteLineProcessor {});
+        assert_eq!("Bob|000042|\n".as_bytes(), &line_processor.process(line, &context)[..]);
+    }
+}
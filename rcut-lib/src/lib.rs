@@ -5,11 +5,37 @@
 
 use std::{cmp, str};
 
+pub mod format;
+mod keys;
+mod layout;
+mod logfmt;
+mod records;
+pub mod stats;
+mod strict;
+mod transform;
+
+pub use format::{JsonKeys, JsonLineProcessor};
+pub use keys::{
+    extract_key_paths, select_values_json, KeyContext, KeyContextT, KeyJsonLineProcessor,
+    KeyPath, KeyProcessor,
+};
+pub use layout::{
+    parse_layout, select_columns, Column, LayoutContext, LayoutContextT, LayoutLineProcessor,
+    LayoutProcessor,
+};
+pub use logfmt::{parse_logfmt, select_values_logfmt, LogfmtLineProcessor};
+pub use records::{byte_records, char_records, cut_records, field_records, CutRecords};
+pub use strict::{FieldCount, StrictFieldLineProcessor};
+pub use transform::{extract_field_transforms, transform_field, FieldTransform, Transform};
+
 extern crate rtools_traits;
 use rtools_traits::{RtoolT, LineProcessorT};
 
+#[cfg(feature = "async")]
+use rtools_traits::AsyncRtoolT;
+
 /// Cargo version specified in the Cargo.toml file
-const VERSION: &'static str = env!("CARGO_PKG_VERSION");
+const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Cargo version specified in the Cargo.toml file
 pub fn version() -> &'static str {
@@ -18,29 +44,32 @@ pub fn version() -> &'static str {
 
 /// Extract ranged pair having the pattern `(\d|\d+-|-\d+|\d+-\d+)`
 pub fn str_to_ranged_pair(char_part: &str) -> (usize, usize) {
-    assert!(char_part != "-", "invalid range with no endpoint: -");
-
-    let str_pos: Vec<&str> = char_part.split("-").collect();
-
-    if str_pos.len() == 1 {
-        let start_pos = char_part.parse::<usize>().unwrap();
-        (start_pos, start_pos)
-    } else {
-        assert!(str_pos.len() == 2);
+    try_str_to_ranged_pair(char_part).unwrap_or_else(|err| panic!("{}", err))
+}
 
-        let start_pos = if str_pos[0].is_empty() {
-            1
-        } else {
-            str_pos[0].parse::<usize>().unwrap()
-        };
+/// Extract ranged pair having the pattern `(\d|\d+-|-\d+|\d+-\d+)`, invalid ranges are errors
+pub fn try_str_to_ranged_pair(char_part: &str) -> Result<(usize, usize), String> {
+    if char_part == "-" {
+        return Err("invalid range with no endpoint: -".to_string());
+    }
 
-        let end_pos = if str_pos[1].is_empty() {
-            std::usize::MAX
-        } else {
-            str_pos[1].parse::<usize>().unwrap()
-        };
+    let parse = |pos: &str| {
+        pos.parse::<usize>()
+            .map_err(|_| format!("invalid range `{}`", char_part))
+    };
+    let str_pos: Vec<&str> = char_part.split("-").collect();
 
-        (start_pos, end_pos)
+    match str_pos[..] {
+        [pos] => {
+            let start_pos = parse(pos)?;
+            Ok((start_pos, start_pos))
+        }
+        [start_pos, end_pos] => {
+            let start_pos = if start_pos.is_empty() { 1 } else { parse(start_pos)? };
+            let end_pos = if end_pos.is_empty() { usize::MAX } else { parse(end_pos)? };
+            Ok((start_pos, end_pos))
+        }
+        _ => Err(format!("invalid range `{}`", char_part)),
     }
 }
 
@@ -48,7 +77,7 @@ pub fn str_to_ranged_pair(char_part: &str) -> (usize, usize) {
 pub fn extract_ranged_pairs(ranged_pairs_str: &str) -> Vec<(usize, usize)> {
     let unsorted_ranged_pairs: Vec<(usize, usize)> = ranged_pairs_str
         .split(",")
-        .map(|char_part| str_to_ranged_pair(char_part))
+        .map(str_to_ranged_pair)
         .filter(|(start_pos, end_pos)| start_pos <= end_pos)
         .collect();
 
@@ -64,13 +93,13 @@ pub fn merge_ranged_pairs(mut unsorted_ranged_pairs: Vec<(usize, usize)>) -> Vec
 
     for ranged_pair in &unsorted_ranged_pairs {
         if ranged_pairs.is_empty() {
-            ranged_pairs.push(ranged_pair.clone());
+            ranged_pairs.push(*ranged_pair);
         } else {
             let last_mut = ranged_pairs.last_mut().unwrap();
 
             // Merge 2 adjacently sorted intervals whenever possible
             if ranged_pair.0 - 1 > last_mut.1 {
-                ranged_pairs.push(ranged_pair.clone());
+                ranged_pairs.push(*ranged_pair);
             } else {
                 last_mut.1 = cmp::max(last_mut.1, ranged_pair.1);
             }
@@ -84,13 +113,11 @@ pub fn merge_ranged_pairs(mut unsorted_ranged_pairs: Vec<(usize, usize)>) -> Vec
 pub fn prepare_ranged_pairs(no_merge: bool, ranged_pairs_str: &str) -> Vec<(usize, usize)> {
     let unsorted_ranged_pairs = extract_ranged_pairs(ranged_pairs_str);
 
-    let ranged_pairs = if no_merge {
+    if no_merge {
         unsorted_ranged_pairs
     } else {
         merge_ranged_pairs(unsorted_ranged_pairs)
-    };
-
-    ranged_pairs
+    }
 }
 
 pub trait CharContextT {
@@ -101,6 +128,16 @@ pub trait FieldContextT {
     fn ranged_pairs(&self) -> &Vec<(usize, usize)>;
 
     fn delim(&self) -> &str;
+
+    /// Transforms of the selected fields, none by default
+    fn transforms(&self) -> &[FieldTransform] {
+        &[]
+    }
+
+    /// Value of the selected fields missing from lines. Missing fields are skipped by default
+    fn fill_missing(&self) -> Option<&str> {
+        None
+    }
 }
 
 pub struct CharContext<'a> {
@@ -109,9 +146,7 @@ pub struct CharContext<'a> {
 
 impl<'a> CharContext<'a> {
     pub fn new(ranged_pairs: &'a Vec<(usize, usize)>) -> CharContext<'a> {
-        CharContext {
-            ranged_pairs: ranged_pairs,
-        }
+        CharContext { ranged_pairs }
     }
 }
 
@@ -124,6 +159,8 @@ impl CharContextT for CharContext<'_> {
 pub struct FieldContext<'a> {
     ranged_pairs: &'a Vec<(usize, usize)>,
     delim: &'a str,
+    transforms: &'a [FieldTransform],
+    fill_missing: Option<&'a str>,
 }
 
 impl<'a> FieldContext<'a> {
@@ -131,6 +168,22 @@ impl<'a> FieldContext<'a> {
         FieldContext {
             ranged_pairs,
             delim,
+            transforms: &[],
+            fill_missing: None,
+        }
+    }
+
+    /// Transform the selected fields before joining them
+    pub fn with_transforms(self, transforms: &'a [FieldTransform]) -> FieldContext<'a> {
+        FieldContext { transforms, ..self }
+    }
+
+    /// Fill the selected fields missing from lines with the value,
+    /// so that all lines yield as many fields
+    pub fn with_fill_missing(self, fill_missing: &'a str) -> FieldContext<'a> {
+        FieldContext {
+            fill_missing: Some(fill_missing),
+            ..self
         }
     }
 }
@@ -143,28 +196,52 @@ impl FieldContextT for FieldContext<'_> {
     fn delim(&self) -> &str {
         self.delim
     }
+
+    fn transforms(&self) -> &[FieldTransform] {
+        self.transforms
+    }
+
+    fn fill_missing(&self) -> Option<&str> {
+        self.fill_missing
+    }
 }
 
 pub struct CharUtf8LineProcessor {}
 
-/// Extract chars from a UTF-8 line within given ranges
-pub fn process_line_by_char_utf8(line: &str, ranged_pairs: &Vec<(usize, usize)>) -> Vec<u8> {
-    let uchars: Vec<char> = line.chars().collect();
-    let mut out_bytes: Vec<u8> = vec![];
-    let char_count = &uchars.len();
-
-    // Handle UTF-8
+/// Select chars from a UTF-8 line within given ranges.
+/// Each range yields a slice borrowed from the line, no newline is appended
+pub fn select_chars_utf8<'a>(line: &'a str, ranged_pairs: &[(usize, usize)]) -> Vec<&'a str> {
+    // Byte offsets of char boundaries allow slicing the line without copying
     // https://stackoverflow.com/questions/51982999/slice-a-string-containing-unicode-chars
     // https://crates.io/crates/unicode-segmentation
+    let char_offsets: Vec<usize> = line.char_indices().map(|(offset, _)| offset).collect();
+    let char_count = char_offsets.len();
+    let mut parts: Vec<&str> = vec![];
 
     for (start_pos, end_pos) in ranged_pairs {
-        let mut char_pos: usize = start_pos.clone();
-
-        while char_pos <= *char_count && char_pos <= *end_pos {
-            let mut dst = [0; 8];
-            out_bytes.extend(uchars[char_pos - 1].encode_utf8(&mut dst).as_bytes());
-            char_pos += 1;
+        if *start_pos > char_count {
+            continue;
         }
+
+        let start_offset = char_offsets[start_pos - 1];
+        let end_offset = if *end_pos < char_count {
+            char_offsets[*end_pos]
+        } else {
+            line.len()
+        };
+
+        parts.push(&line[start_offset..end_offset]);
+    }
+
+    parts
+}
+
+/// Extract chars from a UTF-8 line within given ranges
+pub fn process_line_by_char_utf8(line: &str, ranged_pairs: &[(usize, usize)]) -> Vec<u8> {
+    let mut out_bytes: Vec<u8> = vec![];
+
+    for part in select_chars_utf8(line, ranged_pairs) {
+        out_bytes.extend(part.as_bytes());
     }
 
     out_bytes.extend("\n".as_bytes());
@@ -180,11 +257,12 @@ impl<C: CharContextT> LineProcessorT<C> for CharUtf8LineProcessor {
 
 pub struct ByteLineProcessor {}
 
-/// Extract bytes from a line within given ranges
-pub fn process_line_by_byte(line: &str, ranged_pairs: &Vec<(usize, usize)>) -> Vec<u8> {
-    let mut out_bytes: Vec<u8> = vec![];
+/// Select bytes from a line within given ranges.
+/// Each range yields a slice borrowed from the line, no newline is appended
+pub fn select_bytes<'a>(line: &'a str, ranged_pairs: &[(usize, usize)]) -> Vec<&'a [u8]> {
     let bytes = line.as_bytes();
     let len = &bytes.len();
+    let mut parts: Vec<&[u8]> = vec![];
 
     // Handle ASCII/single-bytes only
     for (start_pos, end_pos) in ranged_pairs {
@@ -192,14 +270,25 @@ pub fn process_line_by_byte(line: &str, ranged_pairs: &Vec<(usize, usize)>) -> V
             break;
         }
 
-        // NOTE: This will panic if multi-byte characters are present
         let final_bytes = if *end_pos < *len {
             &bytes[start_pos - 1..*end_pos]
         } else {
             &bytes[start_pos - 1..]
         };
 
-        out_bytes.extend(final_bytes);
+        parts.push(final_bytes);
+    }
+
+    parts
+}
+
+/// Extract bytes from a line within given ranges
+pub fn process_line_by_byte(line: &str, ranged_pairs: &[(usize, usize)]) -> Vec<u8> {
+    let mut out_bytes: Vec<u8> = vec![];
+
+    // NOTE: Multi-byte characters may be split
+    for part in select_bytes(line, ranged_pairs) {
+        out_bytes.extend(part);
     }
 
     out_bytes.extend("\n".as_bytes());
@@ -217,19 +306,20 @@ pub struct CharProcessor {}
 
 impl<C: CharContextT, P: LineProcessorT<C>> RtoolT<C, P> for CharProcessor {}
 
+#[cfg(feature = "async")]
+impl<C: CharContextT, P: LineProcessorT<C>> AsyncRtoolT<C, P> for CharProcessor {}
+
 pub struct FieldUtf8LineProcessor {}
 
-/// Extract fields from a UTF-8 line within given ranges
-pub fn process_line_by_field_utf8(
-    line: &str,
-    ranged_pairs: &Vec<(usize, usize)>,
+/// Select fields from a UTF-8 line within given ranges along with their field numbers.
+/// Fields are borrowed from the line, no delimiter or newline is appended
+pub fn select_numbered_fields_utf8<'a>(
+    line: &'a str,
+    ranged_pairs: &[(usize, usize)],
     delim: &str,
-) -> Vec<u8> {
-    let mut out_bytes: Vec<u8> = vec![];
-    let delim = delim;
-
+) -> Vec<(usize, &'a str)> {
     let fields: Vec<&str> = line.split(delim).collect();
-    let mut has_written = false;
+    let mut selected: Vec<(usize, &str)> = vec![];
 
     for (start_pos, end_pos) in ranged_pairs {
         let len = &fields.len();
@@ -237,24 +327,70 @@ pub fn process_line_by_field_utf8(
             break;
         }
 
-        let extracted_fields = if *end_pos < *len {
-            &fields[start_pos - 1..*end_pos]
+        let end_pos = cmp::min(*end_pos, *len);
+        selected.extend((*start_pos..=end_pos).map(|field_number| (field_number, fields[field_number - 1])));
+    }
+
+    selected
+}
+
+/// Highest field number filled in when missing from a line, so that huge ranges
+/// like `1-99999999999` cannot exhaust the memory. Fields present in lines are not limited
+pub const MAX_FILLED_FIELD: usize = 65536;
+
+/// Select fields from a UTF-8 line within given ranges along with their field numbers,
+/// including the fields missing from the line as `None` up to `MAX_FILLED_FIELD`.
+/// Open-ended ranges select at least their first field
+pub fn select_numbered_fields_with_missing_utf8<'a>(
+    line: &'a str,
+    ranged_pairs: &[(usize, usize)],
+    delim: &str,
+) -> Vec<(usize, Option<&'a str>)> {
+    let fields: Vec<&str> = line.split(delim).collect();
+    let mut selected: Vec<(usize, Option<&str>)> = vec![];
+
+    for (start_pos, end_pos) in ranged_pairs {
+        let end_pos = if *end_pos == usize::MAX {
+            cmp::max(*start_pos, fields.len())
         } else {
-            &fields[start_pos - 1..]
+            *end_pos
         };
+        let end_pos = cmp::min(end_pos, cmp::max(MAX_FILLED_FIELD, fields.len()));
 
-        for field in extracted_fields {
-            // Delimiter sits between fields
-            if has_written {
-                out_bytes.extend(delim.as_bytes());
-            } else {
-                has_written = true;
-            }
-
-            out_bytes.extend(field.as_bytes());
-        }
+        selected.extend((*start_pos..=end_pos).map(|field_number| (field_number, fields.get(field_number - 1).copied())));
     }
 
+    selected
+}
+
+/// Select fields from a UTF-8 line within given ranges.
+/// Fields are borrowed from the line, no delimiter or newline is appended
+pub fn select_fields_utf8<'a>(
+    line: &'a str,
+    ranged_pairs: &[(usize, usize)],
+    delim: &str,
+) -> Vec<&'a str> {
+    select_numbered_fields_utf8(line, ranged_pairs, delim)
+        .into_iter()
+        .map(|(_, field)| field)
+        .collect()
+}
+
+/// Extract fields from a UTF-8 line within given ranges
+pub fn process_line_by_field_utf8(
+    line: &str,
+    ranged_pairs: &[(usize, usize)],
+    delim: &str,
+) -> Vec<u8> {
+    let mut out_bytes: Vec<u8> = vec![];
+
+    // Delimiter sits between fields
+    out_bytes.extend(
+        select_fields_utf8(line, ranged_pairs, delim)
+            .join(delim)
+            .as_bytes(),
+    );
+
     out_bytes.extend("\n".as_bytes());
     out_bytes
 }
@@ -262,7 +398,23 @@ pub fn process_line_by_field_utf8(
 impl<C: FieldContextT> LineProcessorT<C> for FieldUtf8LineProcessor {
     /// Extract parts of an ASCII encoded line
     fn process(&self, line: &str, context: &C) -> Vec<u8> {
-        process_line_by_field_utf8(line, context.ranged_pairs(), context.delim())
+        let fields: Vec<_> = match context.fill_missing() {
+            None if context.transforms().is_empty() => {
+                return process_line_by_field_utf8(line, context.ranged_pairs(), context.delim());
+            }
+            None => select_numbered_fields_utf8(line, context.ranged_pairs(), context.delim())
+                .into_iter()
+                .map(|(field_number, field)| transform_field(field_number, field, context.transforms()))
+                .collect(),
+            Some(fill) => select_numbered_fields_with_missing_utf8(line, context.ranged_pairs(), context.delim())
+                .into_iter()
+                .map(|(field_number, field)| transform_field(field_number, field.unwrap_or(fill), context.transforms()))
+                .collect(),
+        };
+
+        let mut out_bytes = fields.join(context.delim()).into_bytes();
+        out_bytes.push(b'\n');
+        out_bytes
     }
 }
 
@@ -270,22 +422,25 @@ pub struct FieldProcessor {}
 
 impl<C: FieldContextT, P: LineProcessorT<C>> RtoolT<C, P> for FieldProcessor {}
 
+#[cfg(feature = "async")]
+impl<C: FieldContextT, P: LineProcessorT<C>> AsyncRtoolT<C, P> for FieldProcessor {}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
-    const _STR_RANGES_01: &'static str = "9,4,7,3,12,5-15";
-    const _STR_BIRDS: &'static str = "🦃🐔🐓🐣🐤🐥🐦🐧🕊🦅🦆🦢🦉🦚🦜";
-    const _STR_BIRDS_OUTPUT: &'static str = "🕊🐣🐦🐓🦢🐤🐥🐦🐧🕊🦅🦆🦢🦉🦚🦜\n";
-    const _STR_ALPHABET: &'static str = "abcdefghijklmnopqrstuvwxyz";
-    const _STR_ALPHABET_OUTPUT: &'static str = "idgclefghijklmno\n";
+    const _STR_RANGES_01: &str = "9,4,7,3,12,5-15";
+    const _STR_BIRDS: &str = "🦃🐔🐓🐣🐤🐥🐦🐧🕊🦅🦆🦢🦉🦚🦜";
+    const _STR_BIRDS_OUTPUT: &str = "🕊🐣🐦🐓🦢🐤🐥🐦🐧🕊🦅🦆🦢🦉🦚🦜\n";
+    const _STR_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz";
+    const _STR_ALPHABET_OUTPUT: &str = "idgclefghijklmno\n";
 
     #[test]
     fn test_str_to_ranged_pair_valid_inputs() {
         assert_eq!(str_to_ranged_pair("1"), (1, 1));
         assert_eq!(str_to_ranged_pair("2"), (2, 2));
         assert_eq!(str_to_ranged_pair("-20"), (1, 20));
-        assert_eq!(str_to_ranged_pair("20-"), (20, std::usize::MAX));
+        assert_eq!(str_to_ranged_pair("20-"), (20, usize::MAX));
         assert_eq!(str_to_ranged_pair("3-7"), (3, 7));
     }
 
@@ -326,26 +481,26 @@ mod tests {
         assert_eq!(extract_ranged_pairs("5-9"), vec![(5, 9)]);
         assert_eq!(extract_ranged_pairs("9-5"), vec![]);
         assert_eq!(extract_ranged_pairs("-5"), vec![(1, 5)]);
-        assert_eq!(extract_ranged_pairs("5-"), vec![(5, std::usize::MAX)]);
+        assert_eq!(extract_ranged_pairs("5-"), vec![(5, usize::MAX)]);
     }
 
     #[test]
     fn test_extract_ranged_pairs_ensure_no_sorting() {
         assert_eq!(
             extract_ranged_pairs("3,4,5-"),
-            vec![(3, 3), (4, 4), (5, std::usize::MAX)]
+            vec![(3, 3), (4, 4), (5, usize::MAX)]
         );
         assert_eq!(
             extract_ranged_pairs("5-,3,4"),
-            vec![(5, std::usize::MAX), (3, 3), (4, 4)]
+            vec![(5, usize::MAX), (3, 3), (4, 4)]
         );
         assert_eq!(
             extract_ranged_pairs("6-10,5-"),
-            vec![(6, 10), (5, std::usize::MAX)]
+            vec![(6, 10), (5, usize::MAX)]
         );
         assert_eq!(
             extract_ranged_pairs("7,6-10,5-"),
-            vec![(7, 7), (6, 10), (5, std::usize::MAX)]
+            vec![(7, 7), (6, 10), (5, usize::MAX)]
         );
     }
 
@@ -365,23 +520,23 @@ mod tests {
     fn test_merge_ranged_pairs() {
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("3,4,5-")),
-            vec![(3, std::usize::MAX)]
+            vec![(3, usize::MAX)]
         );
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("3-4,5-")),
-            vec![(3, std::usize::MAX)]
+            vec![(3, usize::MAX)]
         );
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("3-5,5-")),
-            vec![(3, std::usize::MAX)]
+            vec![(3, usize::MAX)]
         );
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("3-6,5-")),
-            vec![(3, std::usize::MAX)]
+            vec![(3, usize::MAX)]
         );
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("7,6-10,5-")),
-            vec![(5, std::usize::MAX)]
+            vec![(5, usize::MAX)]
         );
         assert_eq!(
             merge_ranged_pairs(extract_ranged_pairs("3-7,8,2-10,12-20")),
@@ -478,10 +633,7 @@ mod tests {
             vec![10],
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &ranged_pairs
-                }
+                &FieldContext::new(&ranged_pairs, delim)
             )
         );
     }
@@ -496,10 +648,7 @@ mod tests {
             "1234\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &ranged_pairs
-                }
+                &FieldContext::new(&ranged_pairs, delim)
             )
         );
     }
@@ -514,10 +663,7 @@ mod tests {
             "\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &ranged_pairs
-                }
+                &FieldContext::new(&ranged_pairs, delim)
             )
         );
     }
@@ -531,50 +677,35 @@ mod tests {
             ":2\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 3)]
-                },
+                &FieldContext::new(&vec![(1, 1), (3, 3)], delim),
             )
         );
         assert_eq!(
             ":2:3\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 3), (4, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 3), (4, 4)], delim)
             )
         );
         assert_eq!(
             ":3\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (4, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (4, 4)], delim)
             )
         );
         assert_eq!(
             ":2:3\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 4)], delim)
             )
         );
         assert_eq!(
             ":2:3\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 5)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 5)], delim)
             )
         );
     }
@@ -588,51 +719,155 @@ mod tests {
             ":🐥\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 3)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 3)], delim)
             )
         );
         assert_eq!(
             ":🐥:🐓\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 3), (4, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 3), (4, 4)], delim)
             )
         );
         assert_eq!(
             ":🐓\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (4, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (4, 4)], delim)
             )
         );
         assert_eq!(
             ":🐥:🐓\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 4)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 4)], delim)
             )
         );
         assert_eq!(
             ":🐥:🐓\n".as_bytes().to_vec(),
             line_processor.process(
                 line,
-                &FieldContext {
-                    delim,
-                    ranged_pairs: &vec![(1, 1), (3, 5)]
-                }
+                &FieldContext::new(&vec![(1, 1), (3, 5)], delim)
+            )
+        );
+    }
+
+    #[test]
+    fn test_select_numbered_fields_with_missing_utf8() {
+        assert_eq!(
+            vec![(1, Some("🐣")), (3, None), (5, None), (6, None)],
+            select_numbered_fields_with_missing_utf8("🐣:🐤", &[(1, 1), (3, 3), (5, 6)], ":")
+        );
+        assert_eq!(
+            vec![(2, Some("b")), (3, Some("c"))],
+            select_numbered_fields_with_missing_utf8("a,b,c", &[(2, usize::MAX)], ",")
+        );
+        assert_eq!(
+            vec![(4, None)],
+            select_numbered_fields_with_missing_utf8("a,b,c", &[(4, usize::MAX)], ",")
+        );
+
+        // Huge ranges are filled in up to the limit only
+        let selected = select_numbered_fields_with_missing_utf8("a,b", &[(1, usize::MAX - 1)], ",");
+        assert_eq!(MAX_FILLED_FIELD, selected.len());
+        assert_eq!((2, Some("b")), selected[1]);
+        assert!(select_numbered_fields_with_missing_utf8("a,b", &[(MAX_FILLED_FIELD + 1, usize::MAX)], ",").is_empty());
+    }
+
+    #[test]
+    fn test_process_utf8_fields_with_fill_missing() {
+        let line_processor = FieldUtf8LineProcessor {};
+        let ranged_pairs = vec![(1, 2), (4, 4)];
+
+        let context = FieldContext::new(&ranged_pairs, ",").with_fill_missing("");
+        assert_eq!("🐣,,\n".as_bytes(), &line_processor.process("🐣", &context)[..]);
+        assert_eq!("a,b,d\n".as_bytes(), &line_processor.process("a,b,c,d", &context)[..]);
+
+        let (_, field_transforms) = extract_field_transforms("4:lpad=3").unwrap();
+        let context = FieldContext::new(&ranged_pairs, ",")
+            .with_transforms(&field_transforms)
+            .with_fill_missing("NA");
+        assert_eq!("a,b, NA\n".as_bytes(), &line_processor.process("a,b", &context)[..]);
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_process_lines_utf8_async_with_duplex() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader};
+
+        // Feed the input through one duplex pipe and collect the output from another
+        let (mut in_writer, in_reader) = tokio::io::duplex(64);
+        let (mut out_writer, mut out_reader) = tokio::io::duplex(64);
+
+        let feeder = tokio::spawn(async move {
+            in_writer.write_all(_STR_BIRDS.as_bytes()).await.unwrap();
+            in_writer.write_all(b"\n").await.unwrap();
+            in_writer.write_all(_STR_BIRDS.as_bytes()).await.unwrap();
+        });
+
+        let collector = tokio::spawn(async move {
+            let mut out = Vec::new();
+            out_reader.read_to_end(&mut out).await.unwrap();
+            out
+        });
+
+        let ranged_pairs = extract_ranged_pairs(_STR_RANGES_01);
+        CharProcessor {}
+            .process_lines_async(
+                &CharUtf8LineProcessor {},
+                BufReader::new(in_reader),
+                &mut out_writer,
+                &CharContext::new(&ranged_pairs),
             )
+            .await
+            .unwrap();
+        // Close the output pipe so that the collector sees EOF
+        drop(out_writer);
+
+        feeder.await.unwrap();
+        assert_eq!(
+            [_STR_BIRDS_OUTPUT, _STR_BIRDS_OUTPUT].concat().into_bytes(),
+            collector.await.unwrap()
+        );
+    }
+
+    #[cfg(feature = "async")]
+    #[tokio::test]
+    async fn test_process_fields_async_with_duplex() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (mut in_writer, in_reader) = tokio::io::duplex(16);
+        let (mut out_writer, mut out_reader) = tokio::io::duplex(16);
+
+        let feeder = tokio::spawn(async move {
+            in_writer
+                .write_all(":🐣:🐥:🐓\na:b:c:d\n".as_bytes())
+                .await
+                .unwrap();
+        });
+
+        let collector = tokio::spawn(async move {
+            let mut out = Vec::new();
+            out_reader.read_to_end(&mut out).await.unwrap();
+            out
+        });
+
+        let ranged_pairs = vec![(1, 1), (3, 4)];
+        FieldProcessor {}
+            .process_readable_async(
+                &FieldUtf8LineProcessor {},
+                in_reader,
+                &mut out_writer,
+                &FieldContext::new(&ranged_pairs, ":"),
+            )
+            .await
+            .unwrap();
+        drop(out_writer);
+
+        feeder.await.unwrap();
+        assert_eq!(
+            ":🐥:🐓\na:c:d\n".as_bytes().to_vec(),
+            collector.await.unwrap()
         );
     }
 }
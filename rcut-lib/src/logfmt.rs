@@ -0,0 +1,178 @@
+//! Selection of values from logfmt records by key names,
+//! e.g. `ts=2021-01-02T03:04:05Z level=info msg="user logged in"`.
+//!
+
+use std::borrow::Cow;
+
+use rtools_traits::LineProcessorT;
+
+use crate::format::{Part, SelectT};
+use crate::keys::{join_values, KeyContextT};
+
+/// Parse the logfmt record into its key-value pairs. Values may be quoted
+/// with `"` and contain the escapes `\"`, `\\`, `\n`, `\r` and `\t`.
+/// Keys without `=` have empty values
+pub fn parse_logfmt(line: &str) -> Vec<(&str, Cow<'_, str>)> {
+    let mut pairs = vec![];
+    let mut rest = line.trim_start();
+
+    while !rest.is_empty() {
+        let key_end = rest
+            .find(|c: char| c == '=' || c.is_whitespace())
+            .unwrap_or(rest.len());
+        let key = &rest[..key_end];
+        rest = &rest[key_end..];
+
+        let value = match rest.strip_prefix('=') {
+            Some(after_eq) if after_eq.starts_with('"') => {
+                let (value, after_value) = parse_quoted(&after_eq[1..]);
+                rest = after_value;
+                value
+            }
+            Some(after_eq) => {
+                let value_end = after_eq.find(char::is_whitespace).unwrap_or(after_eq.len());
+                rest = &after_eq[value_end..];
+                Cow::Borrowed(&after_eq[..value_end])
+            }
+            None => Cow::Borrowed(""),
+        };
+
+        if !key.is_empty() {
+            pairs.push((key, value));
+        }
+        rest = rest.trim_start();
+    }
+
+    pairs
+}
+
+/// Parse the quoted value after its opening quote up to its closing quote.
+/// Returns the unescaped value and the rest of the line
+fn parse_quoted(text: &str) -> (Cow<'_, str>, &str) {
+    // Values without escapes are borrowed
+    match text.find(['"', '\\']) {
+        Some(end) if text.as_bytes()[end] == b'"' => {
+            return (Cow::Borrowed(&text[..end]), &text[end + 1..]);
+        }
+        None => return (Cow::Borrowed(text), ""),
+        _ => {}
+    }
+
+    let mut value = String::new();
+    let mut chars = text.char_indices();
+
+    while let Some((index, c)) = chars.next() {
+        match c {
+            '"' => return (Cow::Owned(value), &text[index + 1..]),
+            '\\' => match chars.next() {
+                Some((_, 'n')) => value.push('\n'),
+                Some((_, 'r')) => value.push('\r'),
+                Some((_, 't')) => value.push('\t'),
+                Some((_, c)) => value.push(c),
+                None => value.push('\\'),
+            },
+            c => value.push(c),
+        }
+    }
+
+    // Unterminated quotes run to the end of the line
+    (Cow::Owned(value), "")
+}
+
+/// Select values from a logfmt record by key names. Missing keys yield empty values.
+/// The first occurrence of repeated keys wins
+pub fn select_values_logfmt<'a>(line: &'a str, keys: &[String]) -> Vec<Cow<'a, str>> {
+    let pairs = parse_logfmt(line);
+
+    keys.iter()
+        .map(|key| {
+            pairs
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.clone())
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// Key names of the context. Key paths are matched as whole names since logfmt is flat
+fn key_names<C: KeyContextT>(context: &C) -> Vec<String> {
+    context
+        .key_paths()
+        .iter()
+        .map(|key_path| key_path.join("."))
+        .collect()
+}
+
+pub struct LogfmtLineProcessor {}
+
+impl<C: KeyContextT> LineProcessorT<C> for LogfmtLineProcessor {
+    /// Extract values of a logfmt encoded line
+    fn process(&self, line: &str, context: &C) -> Vec<u8> {
+        join_values(&select_values_logfmt(line, &key_names(context)), context.delim())
+    }
+}
+
+impl<C: KeyContextT> SelectT<C> for LogfmtLineProcessor {
+    fn select_parts<'a>(&self, line: &'a str, context: &C) -> Vec<Part<'a>> {
+        let keys = key_names(context);
+        let values = select_values_logfmt(line, &keys);
+
+        keys.into_iter()
+            .zip(values)
+            .map(|(label, text)| Part { label, text })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::keys::{extract_key_paths, KeyContext};
+
+    #[test]
+    fn test_parse_logfmt() {
+        let line = r#"  ts=2021-01-02 level=info msg="user \"bob\" logged in" debug empty= path="C:\\tmp" "#;
+        let pairs: Vec<(&str, String)> = parse_logfmt(line)
+            .into_iter()
+            .map(|(key, value)| (key, value.into_owned()))
+            .collect();
+
+        assert_eq!(
+            vec![
+                ("ts", "2021-01-02".to_string()),
+                ("level", "info".to_string()),
+                ("msg", "user \"bob\" logged in".to_string()),
+                ("debug", "".to_string()),
+                ("empty", "".to_string()),
+                ("path", "C:\\tmp".to_string()),
+            ],
+            pairs
+        );
+    }
+
+    #[test]
+    fn test_parse_logfmt_quoted() {
+        assert_eq!(
+            vec![("a", Cow::Borrowed("x y")), ("b", Cow::Borrowed("1"))],
+            parse_logfmt(r#"a="x y" b=1"#)
+        );
+        assert_eq!(vec![("a", Cow::Borrowed("🐣 open"))], parse_logfmt(r#"a="🐣 open"#));
+        assert_eq!(
+            vec![("a", Cow::<str>::Owned("l1\nl2".to_string()))],
+            parse_logfmt(r#"a="l1\nl2""#)
+        );
+    }
+
+    #[test]
+    fn test_process_logfmt() {
+        let key_paths = extract_key_paths("level,msg,missing,req.id");
+        let context = KeyContext::new(&key_paths, ",");
+        let line = r#"level=warn msg="disk 90% full" req.id=42 level=info"#;
+
+        assert_eq!(
+            "warn,disk 90% full,,42\n".as_bytes(),
+            &LogfmtLineProcessor {}.process(line, &context)[..]
+        );
+    }
+}
@@ -0,0 +1,119 @@
+//! Iterator adapters for using rcut as a library.
+//! Records are cut from any `BufRead` and yielded without trailing newlines.
+//!
+
+use std::io::{self, BufRead, Lines};
+
+use crate::{select_bytes, select_chars_utf8, select_fields_utf8};
+
+/// Iterator over records cut from the lines of a buffered reader
+pub struct CutRecords<R, F> {
+    lines: Lines<R>,
+    select: F,
+}
+
+impl<R: BufRead, T, F: FnMut(&str) -> T> Iterator for CutRecords<R, F> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let select = &mut self.select;
+        self.lines.next().map(|line| line.map(|line| select(&line)))
+    }
+}
+
+/// Cut records from the input using a custom selection over each line
+pub fn cut_records<R: BufRead, T, F: FnMut(&str) -> T>(input: R, select: F) -> CutRecords<R, F> {
+    CutRecords {
+        lines: input.lines(),
+        select,
+    }
+}
+
+/// Cut chars from each UTF-8 line within given ranges
+pub fn char_records<'a, R: BufRead + 'a>(
+    input: R,
+    ranged_pairs: &'a [(usize, usize)],
+) -> impl Iterator<Item = io::Result<String>> + 'a {
+    cut_records(input, move |line| {
+        select_chars_utf8(line, ranged_pairs).concat()
+    })
+}
+
+/// Cut bytes from each line within given ranges
+pub fn byte_records<'a, R: BufRead + 'a>(
+    input: R,
+    ranged_pairs: &'a [(usize, usize)],
+) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+    cut_records(input, move |line| select_bytes(line, ranged_pairs).concat())
+}
+
+/// Cut fields from each UTF-8 line within given ranges, one `String` per field
+pub fn field_records<'a, R: BufRead + 'a>(
+    input: R,
+    ranged_pairs: &'a [(usize, usize)],
+    delim: &'a str,
+) -> impl Iterator<Item = io::Result<Vec<String>>> + 'a {
+    cut_records(input, move |line| {
+        select_fields_utf8(line, ranged_pairs, delim)
+            .into_iter()
+            .map(String::from)
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_char_records_have_no_newlines() {
+        let input = Cursor::new("🦃🐔🐓🐣\nabcd\n\nxy");
+        let ranged_pairs = vec![(2, 3)];
+        let records: Vec<String> = char_records(input, &ranged_pairs)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec!["🐔🐓", "bc", "", "y"], records);
+    }
+
+    #[test]
+    fn test_byte_records() {
+        let input = Cursor::new("abcdef\r\nghijkl\n");
+        let ranged_pairs = vec![(1, 2), (5, 9)];
+        let records: Vec<Vec<u8>> = byte_records(input, &ranged_pairs)
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(vec![b"abef".to_vec(), b"ghkl".to_vec()], records);
+    }
+
+    #[test]
+    fn test_field_records() {
+        let input = Cursor::new("a,b,c,d\n,🐣,🐥\none\n");
+        let ranged_pairs = vec![(1, 1), (3, 4)];
+        let records: Vec<Vec<String>> = field_records(input, &ranged_pairs, ",")
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(
+            vec![vec!["a", "c", "d"], vec!["", "🐥"], vec!["one"]],
+            records
+        );
+    }
+
+    #[test]
+    fn test_cut_records_with_custom_selection() {
+        let input = Cursor::new("k1=v1;k2=v2\nk3=v3\n");
+        let counts: Vec<usize> =
+            cut_records(input, |line| select_fields_utf8(line, &[(1, 9)], ";").len())
+                .map(Result::unwrap)
+                .collect();
+        assert_eq!(vec![2, 1], counts);
+    }
+
+    #[test]
+    fn test_cut_records_reports_invalid_utf8() {
+        let input = Cursor::new(vec![0xff, 0xfe, b'\n']);
+        let ranged_pairs = vec![(1, 1)];
+        let mut records = char_records(input, &ranged_pairs);
+        assert!(records.next().unwrap().is_err());
+    }
+}
@@ -0,0 +1,272 @@
+//! Statistics of the structure of lines: histograms of field counts
+//! or char/byte lengths, outliers and likely delimiters.
+//!
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+
+use rtools_traits::{LineProcessorT, RecordContext, RtoolT, StatefulProcessorT};
+
+use crate::select_fields_utf8;
+
+/// Delimiters tried when guessing the delimiter of the input
+pub const DELIMITER_CANDIDATES: [char; 6] = [',', '\t', ';', '|', ':', ' '];
+
+/// Delimiters chosen among by `detect_delimiter`, the preferred first
+pub const AUTO_DELIMITERS: [char; 5] = [',', '\t', ';', '|', ' '];
+
+/// How many line numbers are kept as examples of each outlier count
+const OUTLIER_EXAMPLES: usize = 5;
+
+/// What is measured for each line
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Measure {
+    /// Number of fields split by the delimiter, like `process_line_by_field_utf8`
+    Fields(String),
+    /// Number of UTF-8 chars
+    Chars,
+    /// Number of bytes
+    Bytes,
+}
+
+impl Measure {
+    pub fn measure(&self, line: &str) -> usize {
+        match self {
+            Measure::Fields(delim) => select_fields_utf8(line, &[(1, usize::MAX)], delim).len(),
+            Measure::Chars => line.chars().count(),
+            Measure::Bytes => line.len(),
+        }
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Measure::Fields(_) => "fields",
+            Measure::Chars => "chars",
+            Measure::Bytes => "bytes",
+        }
+    }
+}
+
+/// Lines having the same measure
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct Bucket {
+    lines: u64,
+    first_line_numbers: Vec<u64>,
+}
+
+/// Collector of line statistics
+pub struct Stats {
+    measure: Measure,
+    lines: u64,
+    histogram: BTreeMap<usize, Bucket>,
+    // Histograms of the number of occurrences of each delimiter candidate per line
+    candidates: Vec<BTreeMap<usize, u64>>,
+}
+
+impl Stats {
+    pub fn new(measure: Measure) -> Stats {
+        Stats {
+            measure,
+            lines: 0,
+            histogram: BTreeMap::new(),
+            candidates: vec![BTreeMap::new(); DELIMITER_CANDIDATES.len()],
+        }
+    }
+
+    /// Account for the line
+    pub fn add(&mut self, line: &str, line_number: u64) {
+        self.lines += 1;
+
+        let bucket = self.histogram.entry(self.measure.measure(line)).or_default();
+        bucket.lines += 1;
+        if bucket.first_line_numbers.len() < OUTLIER_EXAMPLES {
+            bucket.first_line_numbers.push(line_number);
+        }
+
+        for (candidate, counts) in DELIMITER_CANDIDATES.iter().zip(self.candidates.iter_mut()) {
+            *counts.entry(line.matches(*candidate).count()).or_default() += 1;
+        }
+    }
+
+    /// Delimiter candidates occurring in lines, the most consistent first:
+    /// `(delimiter, most common occurrences per line, share of lines having them)`
+    pub fn delimiter_candidates(&self) -> Vec<(char, usize, f64)> {
+        let mut candidates: Vec<(char, usize, f64)> = DELIMITER_CANDIDATES
+            .iter()
+            .zip(self.candidates.iter())
+            .filter_map(|(candidate, counts)| {
+                let (count, lines) = counts
+                    .iter()
+                    .filter(|(count, _)| **count > 0)
+                    .max_by_key(|(count, lines)| (**lines, **count))?;
+                Some((*candidate, *count, *lines as f64 / self.lines as f64))
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2).then(b.1.cmp(&a.1)));
+        candidates
+    }
+
+    /// Human-readable report of the statistics
+    pub fn report(&self) -> String {
+        let mut out = String::new();
+        let name = self.measure.name();
+        writeln!(out, "lines: {}", self.lines).unwrap();
+
+        let (min, max) = match (self.histogram.iter().next(), self.histogram.iter().last()) {
+            (Some(min), Some(max)) => (min, max),
+            _ => return out,
+        };
+        writeln!(
+            out,
+            "{} per line: min {} (line {}), max {} (line {})",
+            name, min.0, min.1.first_line_numbers[0], max.0, max.1.first_line_numbers[0]
+        )
+        .unwrap();
+
+        // Lines not having the most common measure are outliers
+        let mode = self
+            .histogram
+            .iter()
+            .max_by_key(|(measure, bucket)| (bucket.lines, std::cmp::Reverse(**measure)))
+            .map(|(measure, _)| *measure);
+
+        writeln!(out, "histogram of {}:", name).unwrap();
+        for (measure, bucket) in &self.histogram {
+            write!(out, "  {:>6} {:>10} lines", measure, bucket.lines).unwrap();
+            if Some(*measure) != mode {
+                let line_numbers: Vec<String> =
+                    bucket.first_line_numbers.iter().map(u64::to_string).collect();
+                let more = if bucket.lines > line_numbers.len() as u64 { ", ..." } else { "" };
+                write!(out, " (line {}{})", line_numbers.join(", "), more).unwrap();
+            }
+            out.push('\n');
+        }
+
+        let candidates = self.delimiter_candidates();
+        if !candidates.is_empty() {
+            writeln!(out, "delimiter candidates:").unwrap();
+            for (delimiter, count, share) in candidates {
+                writeln!(
+                    out,
+                    "  {:>6} {:>4} per line on {:.0}% of lines",
+                    format!("{:?}", delimiter),
+                    count,
+                    share * 100.0
+                )
+                .unwrap();
+            }
+        }
+
+        out
+    }
+}
+
+/// Statistics are collected per file and reported at the end of each file
+impl<C> StatefulProcessorT<C> for Stats {
+    fn begin_file(&mut self, _file: &str, _context: &C) -> Vec<u8> {
+        *self = Stats::new(self.measure.clone());
+        vec![]
+    }
+
+    fn process_line(&mut self, line: &str, record: &RecordContext, _context: &C) -> Vec<u8> {
+        self.add(line, record.line_number);
+        vec![]
+    }
+
+    fn end_file(&mut self, _file: &str, _context: &C) -> Vec<u8> {
+        self.report().into_bytes()
+    }
+}
+
+/// Reporter of line statistics per file, driving `Stats` with a `StatefulLineProcessor`
+pub struct StatsProcessor {}
+
+impl<C, P: LineProcessorT<C>> RtoolT<C, P> for StatsProcessor {}
+
+/// Detect the delimiter of the lines among `AUTO_DELIMITERS`:
+/// The one splitting the most lines into the same number of fields wins
+pub fn detect_delimiter<'a, I: IntoIterator<Item = &'a str>>(lines: I) -> Option<char> {
+    let mut stats = Stats::new(Measure::Bytes);
+    for (index, line) in lines.into_iter().enumerate() {
+        stats.add(line, index as u64 + 1);
+    }
+
+    let candidates = stats.delimiter_candidates();
+    AUTO_DELIMITERS
+        .iter()
+        .filter_map(|delimiter| candidates.iter().find(|candidate| candidate.0 == *delimiter))
+        .fold(None, |best: Option<&(char, usize, f64)>, candidate| match best {
+            Some(best) if best.2 >= candidate.2 => Some(best),
+            _ => Some(candidate),
+        })
+        .map(|(delimiter, _, _)| *delimiter)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtools_traits::StatefulLineProcessor;
+    use std::io::{BufWriter, Cursor};
+
+    fn stats(measure: Measure, lines: &[&str]) -> Stats {
+        let mut stats = Stats::new(measure);
+        for (index, line) in lines.iter().enumerate() {
+            stats.add(line, index as u64 + 1);
+        }
+        stats
+    }
+
+    const _LINES: [&str; 5] = ["id,name,city", "1,ab,x", "2,cd", "3,ef,y", "4,\"g,h\",z"];
+
+    #[test]
+    fn test_stats_report_fields() {
+        assert_eq!(
+            "lines: 5\n\
+             fields per line: min 2 (line 3), max 4 (line 5)\n\
+             histogram of fields:\n\
+             \x20      2          1 lines (line 3)\n\
+             \x20      3          3 lines\n\
+             \x20      4          1 lines (line 5)\n\
+             delimiter candidates:\n\
+             \x20    ','    2 per line on 60% of lines\n",
+            stats(Measure::Fields(",".to_string()), &_LINES).report()
+        );
+    }
+
+    #[test]
+    fn test_stats_lengths() {
+        let stats = stats(Measure::Chars, &["🐣🐤", "ab", "a|b|c"]);
+        assert!(stats.report().starts_with("lines: 3\nchars per line: min 2 (line 1), max 5 (line 3)\n"));
+        assert_eq!(vec![('|', 2, 1.0 / 3.0)], stats.delimiter_candidates());
+
+        assert_eq!(8, Measure::Bytes.measure("🐣🐤"));
+    }
+
+    #[test]
+    fn test_detect_delimiter() {
+        assert_eq!(Some(','), detect_delimiter(_LINES.iter().copied()));
+        assert_eq!(Some('\t'), detect_delimiter(vec!["a b\tc", "d\te f g", "h\ti"]));
+        // Ties go to the preferred delimiter
+        assert_eq!(Some(';'), detect_delimiter(vec!["a;b|c", "d;e|f"]));
+        assert_eq!(Some(' '), detect_delimiter(vec!["12:00 up", "13:00 down"]));
+        assert_eq!(None, detect_delimiter(vec!["a", "b"]));
+    }
+
+    #[test]
+    fn test_stats_processor_reports_each_input() {
+        let line_processor = StatefulLineProcessor::new(Stats::new(Measure::Bytes));
+        let mut out = BufWriter::new(vec![]);
+
+        StatsProcessor {}.process_lines(&line_processor, Cursor::new("ab\n🐣\n"), &mut out, &());
+        StatsProcessor {}.process_lines(&line_processor, Cursor::new(""), &mut out, &());
+        let out = String::from_utf8(out.into_inner().unwrap()).unwrap();
+        assert!(out.starts_with("lines: 2\nbytes per line: min 2 (line 1), max 4 (line 2)\n"));
+        assert!(out.ends_with("\nlines: 0\n"));
+    }
+
+    #[test]
+    fn test_stats_empty() {
+        assert_eq!("lines: 0\n", Stats::new(Measure::Bytes).report());
+    }
+}
@@ -0,0 +1,122 @@
+//! Validation of consistent field counts: records having another number of fields
+//! than expected are rejected with an error instead of being cut.
+//!
+
+use std::sync::Mutex;
+
+use rtools_traits::{LineProcessorT, RecordContext, RecordError};
+
+use crate::FieldContextT;
+
+/// Number of fields every record must have
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FieldCount {
+    /// As many fields as the first record
+    First,
+    Exactly(usize),
+}
+
+/// Line processor rejecting records with an unexpected number of fields,
+/// other records are processed by the wrapped line processor
+pub struct StrictFieldLineProcessor<P> {
+    line_processor: P,
+    field_count: FieldCount,
+    // Field count of the first record when expecting as many fields
+    first_count: Mutex<Option<usize>>,
+}
+
+impl<P> StrictFieldLineProcessor<P> {
+    pub fn new(line_processor: P, field_count: FieldCount) -> StrictFieldLineProcessor<P> {
+        StrictFieldLineProcessor {
+            line_processor,
+            field_count,
+            first_count: Mutex::new(None),
+        }
+    }
+}
+
+impl<C: FieldContextT, P: LineProcessorT<C>> LineProcessorT<C> for StrictFieldLineProcessor<P> {
+    /// Process the line without validation, since there is no record to report
+    fn process(&self, line: &str, context: &C) -> Vec<u8> {
+        self.line_processor.process(line, context)
+    }
+
+    fn process_record(&self, line: &str, record: &RecordContext, context: &C) -> Vec<u8> {
+        self.line_processor.process_record(line, record, context)
+    }
+
+    fn try_process_record(&self, line: &str, record: &RecordContext, context: &C) -> Result<Vec<u8>, RecordError> {
+        let count = line.split(context.delim()).count();
+        let expected = match self.field_count {
+            FieldCount::Exactly(expected) => expected,
+            FieldCount::First => *self.first_count.lock().unwrap().get_or_insert(count),
+        };
+
+        if count != expected {
+            return Err(RecordError::new(
+                record,
+                format!("expected {} fields, found {}", expected, count),
+            ));
+        }
+
+        self.line_processor.try_process_record(line, record, context)
+    }
+
+    fn begin_file(&self, file: &str, context: &C) -> Vec<u8> {
+        self.line_processor.begin_file(file, context)
+    }
+
+    fn end_file(&self, file: &str, context: &C) -> Vec<u8> {
+        self.line_processor.end_file(file, context)
+    }
+
+    fn finish(&self, context: &C) -> Vec<u8> {
+        self.line_processor.finish(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{FieldContext, FieldUtf8LineProcessor};
+
+    fn record(line_number: u64) -> RecordContext<'static> {
+        RecordContext {
+            file: "a.csv",
+            line_number,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_strict_fields_first() {
+        let ranged_pairs = vec![(2, 2)];
+        let context = FieldContext::new(&ranged_pairs, ",");
+        let processor = StrictFieldLineProcessor::new(FieldUtf8LineProcessor {}, FieldCount::First);
+
+        assert_eq!(Ok(b"b\n".to_vec()), processor.try_process_record("a,b,c", &record(1), &context));
+        assert_eq!(Ok("🐤\n".as_bytes().to_vec()), processor.try_process_record("🐣,🐤,", &record(2), &context));
+        assert_eq!(
+            "a.csv:3: expected 3 fields, found 2",
+            processor
+                .try_process_record("a,b", &record(3), &context)
+                .unwrap_err()
+                .to_string()
+        );
+    }
+
+    #[test]
+    fn test_strict_fields_exactly() {
+        let ranged_pairs = vec![(1, 1)];
+        let context = FieldContext::new(&ranged_pairs, "\t");
+        let processor = StrictFieldLineProcessor::new(FieldUtf8LineProcessor {}, FieldCount::Exactly(2));
+
+        assert_eq!(
+            Err(RecordError::new(&record(1), "expected 2 fields, found 1")),
+            processor.try_process_record("a", &record(1), &context)
+        );
+        assert_eq!(Ok(b"a\n".to_vec()), processor.try_process_record("a\tb", &record(2), &context));
+        // Plain processing is not validated
+        assert_eq!(b"a\n".to_vec(), processor.process("a", &context));
+    }
+}
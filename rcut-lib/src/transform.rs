@@ -0,0 +1,197 @@
+//! Transformations of selected fields before they are joined,
+//! specified along with the fields, e.g. `-f 1,2:trim,3:upper:lpad=8`.
+//!
+
+use std::borrow::Cow;
+
+/// Transformation of a field value. Widths count chars
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Transform {
+    /// Strip surrounding whitespace
+    Trim,
+    Lower,
+    Upper,
+    /// Pad with spaces on the left up to the width
+    LeftPad(usize),
+    /// Pad with spaces on the right up to the width
+    RightPad(usize),
+    /// Cut values longer than the width, ending them with `…`
+    Truncate(usize),
+}
+
+impl Transform {
+    /// Parse the transform named `trim`, `lower`, `upper`, `lpad=N`, `rpad=N` or `truncate=N`
+    pub fn parse(transform_str: &str) -> Result<Transform, String> {
+        let (name, arg) = match transform_str.split_once('=') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (transform_str, None),
+        };
+
+        let width = || {
+            arg.and_then(|arg| arg.parse::<usize>().ok())
+                .ok_or_else(|| format!("transform `{}` expects a width, e.g. {}=10", name, name))
+        };
+
+        match (name, arg) {
+            ("trim", None) => Ok(Transform::Trim),
+            ("lower", None) => Ok(Transform::Lower),
+            ("upper", None) => Ok(Transform::Upper),
+            ("lpad", _) => Ok(Transform::LeftPad(width()?)),
+            ("rpad", _) => Ok(Transform::RightPad(width()?)),
+            ("truncate", _) => Ok(Transform::Truncate(width()?)),
+            _ => Err(format!("unknown transform `{}`", transform_str)),
+        }
+    }
+
+    pub fn apply<'a>(&self, value: Cow<'a, str>) -> Cow<'a, str> {
+        match *self {
+            Transform::Trim => match value {
+                Cow::Borrowed(value) => Cow::Borrowed(value.trim()),
+                Cow::Owned(value) => Cow::Owned(value.trim().to_string()),
+            },
+            Transform::Lower => Cow::Owned(value.to_lowercase()),
+            Transform::Upper => Cow::Owned(value.to_uppercase()),
+            Transform::LeftPad(width) => match width.checked_sub(value.chars().count()) {
+                Some(padding) if padding > 0 => Cow::Owned(format!("{}{}", " ".repeat(padding), value)),
+                _ => value,
+            },
+            Transform::RightPad(width) => match width.checked_sub(value.chars().count()) {
+                Some(padding) if padding > 0 => Cow::Owned(format!("{}{}", value, " ".repeat(padding))),
+                _ => value,
+            },
+            Transform::Truncate(width) => {
+                if value.chars().count() <= width {
+                    value
+                } else if width == 0 {
+                    Cow::Borrowed("")
+                } else {
+                    let mut truncated: String = value.chars().take(width - 1).collect();
+                    truncated.push('…');
+                    Cow::Owned(truncated)
+                }
+            }
+        }
+    }
+}
+
+/// Transforms applied to the fields within the range, in order
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct FieldTransform {
+    pub ranged_pair: (usize, usize),
+    pub transforms: Vec<Transform>,
+}
+
+/// Split the list of fields with transforms, e.g. `1,2:trim,3-4:upper:lpad=8`,
+/// into the plain list of fields and the transforms of their ranges. Invalid ranges are errors
+pub fn extract_field_transforms(fields_str: &str) -> Result<(String, Vec<FieldTransform>), String> {
+    let mut ranges: Vec<&str> = vec![];
+    let mut field_transforms = vec![];
+
+    for field_str in fields_str.split(',') {
+        let mut parts = field_str.split(':');
+        let range = parts.next().unwrap_or_default();
+        let transforms = parts.map(Transform::parse).collect::<Result<Vec<_>, _>>()?;
+
+        let ranged_pair = crate::try_str_to_ranged_pair(range)?;
+        ranges.push(range);
+        if !transforms.is_empty() {
+            field_transforms.push(FieldTransform { ranged_pair, transforms });
+        }
+    }
+
+    Ok((ranges.join(","), field_transforms))
+}
+
+/// Apply the transforms of all ranges containing the field number to the field
+pub fn transform_field<'a>(
+    field_number: usize,
+    field: &'a str,
+    field_transforms: &[FieldTransform],
+) -> Cow<'a, str> {
+    field_transforms
+        .iter()
+        .filter(|field_transform| {
+            let (start_pos, end_pos) = field_transform.ranged_pair;
+            start_pos <= field_number && field_number <= end_pos
+        })
+        .flat_map(|field_transform| field_transform.transforms.iter())
+        .fold(Cow::Borrowed(field), |value, transform| transform.apply(value))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{prepare_ranged_pairs, FieldContext, FieldUtf8LineProcessor};
+    use rtools_traits::LineProcessorT;
+
+    fn apply(transform_str: &str, value: &str) -> String {
+        Transform::parse(transform_str)
+            .unwrap()
+            .apply(Cow::Borrowed(value))
+            .into_owned()
+    }
+
+    #[test]
+    fn test_transforms() {
+        assert_eq!("a b", apply("trim", " \ta b "));
+        assert_eq!("straße", apply("lower", "STRAßE"));
+        assert_eq!("🐣ABC", apply("upper", "🐣abc"));
+        assert_eq!("  🐣x", apply("lpad=4", "🐣x"));
+        assert_eq!("🐣x  ", apply("rpad=4", "🐣x"));
+        assert_eq!("toolong", apply("lpad=3", "toolong"));
+        assert_eq!("🐣🐤…", apply("truncate=3", "🐣🐤🐥🐦"));
+        assert_eq!("abc", apply("truncate=3", "abc"));
+        assert_eq!("", apply("truncate=0", "abc"));
+    }
+
+    #[test]
+    fn test_parse_transform_errors() {
+        assert!(Transform::parse("shout").is_err());
+        assert!(Transform::parse("lpad").is_err());
+        assert!(Transform::parse("lpad=x").is_err());
+        assert!(Transform::parse("trim=1").is_err());
+        assert!(extract_field_transforms("x:trim").is_err());
+        assert!(extract_field_transforms("1-2-3:upper").is_err());
+        assert!(extract_field_transforms("1,x").is_err());
+    }
+
+    #[test]
+    fn test_extract_field_transforms() {
+        let (ranges, field_transforms) = extract_field_transforms("1,2:trim,3-4:upper:lpad=8").unwrap();
+        assert_eq!("1,2,3-4", ranges);
+        assert_eq!(
+            vec![
+                FieldTransform {
+                    ranged_pair: (2, 2),
+                    transforms: vec![Transform::Trim],
+                },
+                FieldTransform {
+                    ranged_pair: (3, 4),
+                    transforms: vec![Transform::Upper, Transform::LeftPad(8)],
+                },
+            ],
+            field_transforms
+        );
+        assert!(extract_field_transforms("2:nope").is_err());
+    }
+
+    #[test]
+    fn test_transform_field() {
+        let (_, field_transforms) = extract_field_transforms("1-:trim,2:upper").unwrap();
+        assert_eq!("a", transform_field(1, " a ", &field_transforms));
+        assert_eq!("B", transform_field(2, " b ", &field_transforms));
+        assert_eq!(Cow::Borrowed("c"), transform_field(3, "c", &field_transforms));
+    }
+
+    #[test]
+    fn test_process_fields_with_transforms() {
+        let (ranges, field_transforms) = extract_field_transforms("1:upper,2:trim:rpad=4,3").unwrap();
+        let ranged_pairs = prepare_ranged_pairs(false, &ranges);
+        let context = FieldContext::new(&ranged_pairs, ",").with_transforms(&field_transforms);
+
+        assert_eq!(
+            "ID,ab  , c \n".as_bytes(),
+            &FieldUtf8LineProcessor {}.process("id, ab , c ,d", &context)[..]
+        );
+    }
+}
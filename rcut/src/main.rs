@@ -2,22 +2,151 @@ extern crate clap;
 use clap::{App, Arg};
 
 extern crate rtools_traits;
-use rtools_traits::{RtoolT};
+use rtools_traits::encoding::{encoding_for_label, Encoding};
+use rtools_traits::filter::{Pattern, Regex};
+use rtools_traits::{
+    peek_lines, OutputTarget, RecordFilter, RtoolOptions, RtoolT, StatefulLineProcessor,
+    STDIN_FILE_NAME,
+};
 
 extern crate rcut_lib;
 use rcut_lib::{
-    prepare_ranged_pairs, version, ByteLineProcessor, CharContext, CharProcessor,
-    CharUtf8LineProcessor, FieldContext, FieldProcessor, FieldUtf8LineProcessor,
+    extract_field_transforms, extract_key_paths, parse_layout, prepare_ranged_pairs,
+    select_columns, version, ByteLineProcessor, CharContext, CharProcessor, CharUtf8LineProcessor,
+    Column, FieldContext, FieldCount, FieldProcessor, FieldUtf8LineProcessor, JsonKeys,
+    JsonLineProcessor, KeyContext, KeyJsonLineProcessor, KeyProcessor, LayoutContext,
+    LayoutLineProcessor, LayoutProcessor, LogfmtLineProcessor, StrictFieldLineProcessor,
+    MAX_FILLED_FIELD,
 };
+use rcut_lib::stats::{detect_delimiter, Measure, Stats, StatsProcessor};
+
+mod table;
+
+use table::TableLineProcessor;
+
+/// Look up the encoding label or exit
+fn to_encoding(label: &str) -> &'static Encoding {
+    encoding_for_label(label).unwrap_or_else(|| {
+        eprintln!("Unknown encoding `{}`!", label);
+        std::process::exit(1);
+    })
+}
+
+/// Parse the line number given to the flag or exit
+fn to_line_number(flag: &str, value: &str) -> u64 {
+    value.parse::<u64>().unwrap_or_else(|_| {
+        eprintln!("The flag --{} expects a line number, got `{}`!", flag, value);
+        std::process::exit(1);
+    })
+}
+
+/// Compile the regex pattern or exit
+fn to_regex_pattern(regex: &str) -> Pattern {
+    match Regex::new(regex) {
+        Ok(regex) => Pattern::Regex(regex),
+        Err(err) => {
+            eprintln!("Invalid regex `{}`: {}", regex, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Parse the layout given inline or in a file and select its columns by names or exit
+fn to_columns(layout: Option<&str>, layout_file: Option<&str>, names: Option<&str>) -> Vec<Column> {
+    let layout_str = match (layout, layout_file) {
+        (Some(layout), _) => layout.to_string(),
+        (None, Some(layout_file)) => std::fs::read_to_string(layout_file).unwrap_or_else(|err| {
+            eprintln!("Could not read the layout file `{}`. The error: {}", layout_file, err);
+            std::process::exit(1);
+        }),
+        (None, None) => unreachable!(),
+    };
+
+    let columns = parse_layout(&layout_str).and_then(|layout| match names {
+        Some(names) => select_columns(&layout, names),
+        None => Ok(layout),
+    });
+
+    columns.unwrap_or_else(|err| {
+        eprintln!("Invalid layout: {}!", err);
+        std::process::exit(1);
+    })
+}
+
+/// Value of -d/--delimiter asking to detect the delimiter
+const AUTO_DELIMITER: &str = "auto";
+
+/// Number of lines sniffed to detect the delimiter
+const SNIFF_LINES: usize = 100;
+
+/// Detect the delimiter from the first lines of the first input if asked for, or exit.
+/// Falls back to TAB when no delimiter is consistent
+fn to_delimiter(delim: &str, files: &[&str], options: &RtoolOptions, verbose: bool) -> String {
+    if delim != AUTO_DELIMITER {
+        return delim.to_string();
+    }
+
+    let file = files.first().copied().unwrap_or(STDIN_FILE_NAME);
+    let lines = peek_lines(file, SNIFF_LINES, options).unwrap_or_else(|err| {
+        eprintln!("Could not read the file `{}`. The error: {}", file, err);
+        std::process::exit(1);
+    });
+
+    match detect_delimiter(lines.iter().map(String::as_str)) {
+        Some(delim) => {
+            if verbose {
+                eprintln!("rcut: detected the delimiter {:?}", delim);
+            }
+            delim.to_string()
+        }
+        None => {
+            if verbose {
+                eprintln!("rcut: no delimiter detected, using '\\t'");
+            }
+            "\t".to_string()
+        }
+    }
+}
 
 /// Perform operations similar to GNU cut
 pub fn do_rcut(input_args: &Vec<&str>) {
-    const _STR_BYTES: &'static str = "bytes";
-    const _STR_CHARACTERS: &'static str = "characters";
-    const _STR_DELIMITER: &'static str = "delimiter";
-    const _STR_FIELDS: &'static str = "fields";
-    const _STR_ASCII: &'static str = "ascii";
-    const _STR_NO_MERGE: &'static str = "no-merge";
+    const _STR_BYTES: &str = "bytes";
+    const _STR_CHARACTERS: &str = "characters";
+    const _STR_DELIMITER: &str = "delimiter";
+    const _STR_FIELDS: &str = "fields";
+    const _STR_KEYS: &str = "keys";
+    const _STR_LOGFMT: &str = "logfmt";
+    const _STR_OUTPUT_DELIMITER: &str = "output-delimiter";
+    const _STR_LAYOUT: &str = "layout";
+    const _STR_LAYOUT_FILE: &str = "layout-file";
+    const _STR_COLUMNS: &str = "columns";
+    const _STR_TRIM: &str = "trim";
+    const _STR_ASCII: &str = "ascii";
+    const _STR_NO_MERGE: &str = "no-merge";
+    const _STR_JSON: &str = "json";
+    const _STR_TABLE: &str = "table";
+    const _STR_STRICT_FIELDS: &str = "strict-fields";
+    const _STR_FILL_MISSING: &str = "fill-missing";
+    const _STR_STATS: &str = "stats";
+    const _STR_DECOMPRESS: &str = "decompress";
+    const _STR_NO_DECOMPRESS: &str = "no-decompress";
+    const _STR_ENCODING: &str = "encoding";
+    const _STR_OUTPUT_ENCODING: &str = "output-encoding";
+    const _STR_OUTPUT: &str = "output";
+    const _STR_IN_PLACE: &str = "in-place";
+    const _STR_OUTPUT_TEMPLATE: &str = "output-template";
+    const _STR_WITH_FILENAME: &str = "with-filename";
+    const _STR_FILE_HEADERS: &str = "file-headers";
+    const _STR_LINE_NUMBER: &str = "line-number";
+    const _STR_BYTE_OFFSET: &str = "byte-offset";
+    const _STR_SKIP_LINES: &str = "skip-lines";
+    const _STR_STOP_AFTER: &str = "stop-after";
+    const _STR_MATCH: &str = "match";
+    const _STR_REGEX: &str = "regex";
+    const _STR_INVERT_MATCH: &str = "invert-match";
+    const _STR_FOLLOW: &str = "follow";
+    const _STR_LINE_BUFFERED: &str = "line-buffered";
+    const _STR_VERBOSE: &str = "verbose";
 
     let matches = App::new("rcut")
         .version(version())
@@ -34,7 +163,7 @@ pub fn do_rcut(input_args: &Vec<&str>) {
                        Sample ranges: 5; 3-7,9; -5; 5-; 4,8-; -4,8.",
                 )
                 .next_line_help(true)
-                .conflicts_with_all(&vec![_STR_DELIMITER, _STR_CHARACTERS])
+                .conflicts_with_all(&[_STR_DELIMITER, _STR_CHARACTERS])
                 .required(false)
                 .takes_value(true),
         )
@@ -49,7 +178,7 @@ pub fn do_rcut(input_args: &Vec<&str>) {
                        Sample ranges: 5; 3-7,9; -5; 5-; 4,8-; -4,8.",
                 )
                 .next_line_help(true)
-                .conflicts_with_all(&vec![_STR_DELIMITER, _STR_BYTES])
+                .conflicts_with_all(&[_STR_DELIMITER, _STR_BYTES])
                 .required(false)
                 .takes_value(true),
         )
@@ -59,7 +188,9 @@ pub fn do_rcut(input_args: &Vec<&str>) {
                 .long(_STR_DELIMITER)
                 .help(
                     "Split lines into fields delimited by given delimiter.\n\
-                     Must be followed by list of fields. E.g. -f2,6-8.",
+                     Must be followed by list of fields. E.g. -f2,6-8.\n\
+                     The delimiter auto is detected from the first lines\n\
+                     among , TAB ; | and SPACE.",
                 )
                 .next_line_help(true)
                 .required(false)
@@ -74,7 +205,99 @@ pub fn do_rcut(input_args: &Vec<&str>) {
                     "Select only these ranges of **fields**.\n\
                        Is dependent on the delimiter flag -d.\n\
                        Ranges are comma-separated.\n\
-                       Sample ranges: 5; 3-7,9; -5; 5-; 4,8-; -4,8.",
+                       Sample ranges: 5; 3-7,9; -5; 5-; 4,8-; -4,8.\n\
+                       Ranges may be followed by :-separated transforms:\n\
+                       trim, lower, upper, lpad=N, rpad=N, truncate=N.\n\
+                       E.g. -f 1,2:trim,3:upper:lpad=8.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_KEYS)
+                .short("k")
+                .long(_STR_KEYS)
+                .value_name("LIST")
+                .help(
+                    "Treat lines as JSON and select only the values of these keys.\n\
+                       Keys are comma-separated, nested keys are dot-separated.\n\
+                       Values are delimited by --output-delimiter, TAB by default.\n\
+                       Backslashes, TABs, line breaks and the delimiter in values\n\
+                       are escaped by a backslash.\n\
+                       Missing keys yield empty values. E.g. -k user.id,status.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[_STR_BYTES, _STR_CHARACTERS, _STR_FIELDS, _STR_DELIMITER])
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_LOGFMT)
+                .long(_STR_LOGFMT)
+                .help(
+                    "Treat lines as logfmt (key=value pairs) instead of JSON.\n\
+                       Values may be quoted. Is dependent on the keys flag -k.",
+                )
+                .next_line_help(true)
+                .requires(_STR_KEYS)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_LAYOUT)
+                .long(_STR_LAYOUT)
+                .value_name("LAYOUT")
+                .help(
+                    "Split fixed-width lines into named columns of chars.\n\
+                       Columns are comma-separated name:range pairs.\n\
+                       With -a/--ascii, ranges are in bytes.\n\
+                       E.g. --layout name:1-20,amount:21-32.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[_STR_BYTES, _STR_CHARACTERS, _STR_FIELDS, _STR_KEYS, _STR_LAYOUT_FILE])
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_LAYOUT_FILE)
+                .long(_STR_LAYOUT_FILE)
+                .value_name("FILE")
+                .help(
+                    "Read the --layout from FILE, one or more columns per line.\n\
+                       Lines starting with # are ignored.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[_STR_BYTES, _STR_CHARACTERS, _STR_FIELDS, _STR_KEYS])
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_COLUMNS)
+                .long(_STR_COLUMNS)
+                .value_name("NAMES")
+                .help(
+                    "Select only these comma-separated columns of the layout.\n\
+                       All columns are selected by default.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_TRIM)
+                .long(_STR_TRIM)
+                .help("Trim the padding around values of layout columns.")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_OUTPUT_DELIMITER)
+                .long(_STR_OUTPUT_DELIMITER)
+                .value_name("STRING")
+                .help(
+                    "Delimit values selected by -k/--keys or layout columns\n\
+                       by STRING, TAB by default.",
                 )
                 .next_line_help(true)
                 .required(false)
@@ -101,11 +324,313 @@ pub fn do_rcut(input_args: &Vec<&str>) {
                 .required(false)
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name(_STR_DECOMPRESS)
+                .long(_STR_DECOMPRESS)
+                .help(
+                    "Decompress inputs compressed with gzip/zstd/xz/bzip2.\n\
+                    Compression is auto-detected by magic bytes.\n\
+                    On by default when built with the decompress feature.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_NO_DECOMPRESS)
+                .long(_STR_NO_DECOMPRESS)
+                .help("Read compressed inputs as they are.")
+                .next_line_help(true)
+                .conflicts_with(_STR_DECOMPRESS)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_ENCODING)
+                .long(_STR_ENCODING)
+                .value_name("LABEL")
+                .help(
+                    "Decode inputs from this encoding into UTF-8.\n\
+                    A byte order mark takes precedence if present.\n\
+                    Without this flag, UTF-8 and UTF-16 BOMs are sniffed.\n\
+                    Sample labels: utf-16le, shift_jis, gbk, latin1.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_OUTPUT_ENCODING)
+                .long(_STR_OUTPUT_ENCODING)
+                .value_name("LABEL")
+                .help(
+                    "Encode outputs from UTF-8 into this encoding.\n\
+                    Unmappable characters are written as &#NNNN;.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_JSON)
+                .long(_STR_JSON)
+                .value_name("KEYS")
+                .help(
+                    "Write each line as a JSON array of the selected parts.\n\
+                    With KEYS, write JSON objects keyed by the field numbers,\n\
+                    ranges or keys (numbers), or by the parts selected from\n\
+                    the first processed line of each file (header).\n\
+                    E.g. --json=header",
+                )
+                .next_line_help(true)
+                .possible_values(&["numbers", "header"])
+                .required(false)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+        )
+        .arg(
+            Arg::with_name(_STR_STATS)
+                .long(_STR_STATS)
+                .help(
+                    "Report statistics of the lines instead of cutting them:\n\
+                    a histogram of field counts with -d, byte lengths with -a,\n\
+                    char lengths otherwise, outliers and likely delimiters.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[
+                    _STR_BYTES,
+                    _STR_CHARACTERS,
+                    _STR_FIELDS,
+                    _STR_KEYS,
+                    _STR_LAYOUT,
+                    _STR_LAYOUT_FILE,
+                    _STR_JSON,
+                    _STR_TABLE,
+                    _STR_STRICT_FIELDS,
+                    _STR_FILL_MISSING,
+                    _STR_OUTPUT,
+                    _STR_IN_PLACE,
+                    _STR_OUTPUT_TEMPLATE,
+                    _STR_FOLLOW,
+                ])
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_TABLE)
+                .long(_STR_TABLE)
+                .value_name("LINES")
+                .help(
+                    "Align the selected fields into columns, like column -t.\n\
+                    Widths are computed over all lines, or over each\n\
+                    window of LINES lines to bound memory.\n\
+                    Each output of --in-place or --output-template is a table.\n\
+                    Followed files need LINES since they never end.\n\
+                    E.g. --table=1000",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[
+                    _STR_JSON,
+                    _STR_WITH_FILENAME,
+                    _STR_FILE_HEADERS,
+                    _STR_LINE_NUMBER,
+                    _STR_BYTE_OFFSET,
+                ])
+                .required(false)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+        )
+        .arg(
+            Arg::with_name(_STR_STRICT_FIELDS)
+                .long(_STR_STRICT_FIELDS)
+                .value_name("N")
+                .help(
+                    "Fail on the first line having another number of fields\n\
+                    than the first line, or than N, reporting its file\n\
+                    and line number. E.g. --strict-fields=12",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+        )
+        .arg(
+            Arg::with_name(_STR_FILL_MISSING)
+                .long(_STR_FILL_MISSING)
+                .value_name("VALUE")
+                .help(
+                    "Output selected fields missing from a line as VALUE,\n\
+                    empty by default, so that all lines have as many fields.\n\
+                    Open-ended ranges yield at least their first field.\n\
+                    Fields are filled in up to the field 65536.\n\
+                    E.g. --fill-missing=NA",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true),
+        )
+        .arg(
+            Arg::with_name(_STR_OUTPUT)
+                .short("o")
+                .long(_STR_OUTPUT)
+                .value_name("FILE")
+                .help(
+                    "Write results to FILE instead of STDOUT.\n\
+                    FILE is written atomically, it is left untouched on errors.\n\
+                    Devices and FIFOs, e.g. /dev/null, are written directly.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[_STR_IN_PLACE, _STR_OUTPUT_TEMPLATE])
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_IN_PLACE)
+                .long(_STR_IN_PLACE)
+                .value_name("SUFFIX")
+                .help(
+                    "Replace each input file by its results atomically.\n\
+                    Keep a backup of each file if SUFFIX is given.\n\
+                    Compressed files are rejected. E.g. --in-place=.bak",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true)
+                .min_values(0)
+                .require_equals(true)
+                .conflicts_with(_STR_OUTPUT_TEMPLATE),
+        )
+        .arg(
+            Arg::with_name(_STR_OUTPUT_TEMPLATE)
+                .long(_STR_OUTPUT_TEMPLATE)
+                .value_name("TEMPLATE")
+                .help(
+                    "Write results of each input file to its own file.\n\
+                    Placeholders {name}, {stem} and {ext} stand for\n\
+                    the input file name, its stem and its extension.\n\
+                    Relative names are siblings of the input file.\n\
+                    Outputs may not overwrite inputs or each other.\n\
+                    E.g. --output-template {stem}.cut.{ext}",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_WITH_FILENAME)
+                .short("H")
+                .long(_STR_WITH_FILENAME)
+                .help("Prefix each record with its file name.")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_FILE_HEADERS)
+                .long(_STR_FILE_HEADERS)
+                .help("Write a header ==> FILE <== before each file.")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_LINE_NUMBER)
+                .short("n")
+                .long(_STR_LINE_NUMBER)
+                .help("Prefix each record with its line number.")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_BYTE_OFFSET)
+                .long(_STR_BYTE_OFFSET)
+                .help("Prefix each record with its byte offset.")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_SKIP_LINES)
+                .long(_STR_SKIP_LINES)
+                .value_name("N")
+                .help("Skip the first N lines of each file, e.g. headers.")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_STOP_AFTER)
+                .long(_STR_STOP_AFTER)
+                .value_name("M")
+                .help("Stop reading each file after line M.")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_MATCH)
+                .long(_STR_MATCH)
+                .value_name("TEXT")
+                .help("Process only lines containing TEXT.")
+                .conflicts_with(_STR_REGEX)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_REGEX)
+                .long(_STR_REGEX)
+                .value_name("REGEX")
+                .help("Process only lines matching REGEX.")
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_INVERT_MATCH)
+                .long(_STR_INVERT_MATCH)
+                .help(
+                    "Process only lines not matching --match or --regex.",
+                )
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_FOLLOW)
+                .long(_STR_FOLLOW)
+                .help(
+                    "Keep processing lines appended to the files, like tail -f.\n\
+                    Truncated and rotated files are read from the start.\n\
+                    Files are read as UTF-8, compressed files are rejected.",
+                )
+                .next_line_help(true)
+                .conflicts_with_all(&[_STR_IN_PLACE, _STR_OUTPUT_TEMPLATE, _STR_DECOMPRESS, _STR_ENCODING])
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_LINE_BUFFERED)
+                .long(_STR_LINE_BUFFERED)
+                .help(
+                    "Flush the output after each line.\n\
+                    Always on when STDOUT is a terminal.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_VERBOSE)
+                .long(_STR_VERBOSE)
+                .help("Report decisions such as the detected delimiter on STDERR.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
         .arg(
             Arg::with_name("files")
                 .help(
                     "The content of these files will be used.\n\
-                     If no files given, STDIN will be used.",
+                     If no files given, STDIN will be used.\n\
+                     The file - stands for STDIN.",
                 )
                 .next_line_help(true)
                 .required(false)
@@ -115,10 +640,25 @@ pub fn do_rcut(input_args: &Vec<&str>) {
 
     let byte_mode = matches.is_present(_STR_BYTES);
     let char_mode = matches.is_present(_STR_CHARACTERS);
+    let key_mode = matches.is_present(_STR_KEYS);
+    let layout_mode = matches.is_present(_STR_LAYOUT) || matches.is_present(_STR_LAYOUT_FILE);
     let field_mode = matches.is_present(_STR_DELIMITER);
+    let stats_mode = matches.is_present(_STR_STATS);
+
+    if !byte_mode && !char_mode && !field_mode && !key_mode && !layout_mode && !stats_mode {
+        eprintln!(
+            "One of -b/--bytes or -c/--characters or -d/--delimiter or -k/--keys or --layout must be present!"
+        );
+        std::process::exit(1);
+    }
 
-    if !byte_mode && !char_mode && !field_mode {
-        eprintln!("One of -b/--bytes or -c/--characters or -d/--delimiter must be present!");
+    if matches.is_present(_STR_OUTPUT_DELIMITER) && !key_mode && !layout_mode {
+        eprintln!("The flag --output-delimiter is dependent on the flag -k/--keys or --layout!");
+        std::process::exit(1);
+    }
+
+    if (matches.is_present(_STR_COLUMNS) || matches.is_present(_STR_TRIM)) && !layout_mode {
+        eprintln!("The flags --columns and --trim are dependent on the flag --layout!");
         std::process::exit(1);
     }
 
@@ -127,29 +667,238 @@ pub fn do_rcut(input_args: &Vec<&str>) {
         std::process::exit(1);
     }
 
-    if field_mode && !matches.is_present(_STR_FIELDS) {
+    if field_mode && !stats_mode && !matches.is_present(_STR_FIELDS) {
         eprintln!("The flag -d/--delimiter requires presence of -f/--fields!");
         std::process::exit(1);
     }
 
     let ascii_mode = matches.is_present(_STR_ASCII);
     let no_merge = matches.is_present(_STR_NO_MERGE);
+    let options = RtoolOptions {
+        decompress: matches.is_present(_STR_DECOMPRESS)
+            || (cfg!(feature = "decompress") && !matches.is_present(_STR_NO_DECOMPRESS)),
+        encoding: matches.value_of(_STR_ENCODING).map(to_encoding),
+        keep_bom: false,
+        output_encoding: matches.value_of(_STR_OUTPUT_ENCODING).map(to_encoding),
+        output: if let Some(path) = matches.value_of(_STR_OUTPUT) {
+            OutputTarget::File(path.into())
+        } else if matches.is_present(_STR_IN_PLACE) {
+            OutputTarget::InPlace {
+                backup_suffix: matches.value_of(_STR_IN_PLACE).map(String::from),
+            }
+        } else if let Some(template) = matches.value_of(_STR_OUTPUT_TEMPLATE) {
+            OutputTarget::PerFile {
+                template: template.to_string(),
+            }
+        } else {
+            OutputTarget::Stdout
+        },
+        with_filename: matches.is_present(_STR_WITH_FILENAME),
+        file_headers: matches.is_present(_STR_FILE_HEADERS),
+        line_numbers: matches.is_present(_STR_LINE_NUMBER),
+        byte_offsets: matches.is_present(_STR_BYTE_OFFSET),
+        filter: RecordFilter {
+            skip_lines: matches
+                .value_of(_STR_SKIP_LINES)
+                .map_or(0, |n| to_line_number(_STR_SKIP_LINES, n)),
+            stop_after: matches
+                .value_of(_STR_STOP_AFTER)
+                .map(|m| to_line_number(_STR_STOP_AFTER, m)),
+            pattern: if let Some(text) = matches.value_of(_STR_MATCH) {
+                Some(Pattern::Literal(text.to_string()))
+            } else {
+                matches.value_of(_STR_REGEX).map(to_regex_pattern)
+            },
+            invert_match: matches.is_present(_STR_INVERT_MATCH),
+        },
+        follow: matches.is_present(_STR_FOLLOW),
+        line_buffered: matches.is_present(_STR_LINE_BUFFERED),
+        peeked: Default::default(),
+    };
 
     // NOTE: Use `values_of` instead of `value_of`!!!!
-    let files_it_opt = matches.values_of("files");
-    let files = if files_it_opt.is_none() {
-        vec![]
+    let files = match matches.values_of("files") {
+        Some(files_it) => files_it.collect(),
+        None => vec![],
+    };
+
+    let verbose = matches.is_present(_STR_VERBOSE);
+
+    if stats_mode {
+        let measure = match matches.value_of(_STR_DELIMITER) {
+            Some(delim) => Measure::Fields(to_delimiter(delim, &files, &options, verbose)),
+            None if ascii_mode => Measure::Bytes,
+            None => Measure::Chars,
+        };
+        let files = if files.is_empty() { vec![STDIN_FILE_NAME] } else { files };
+        // Reports are headed by their file names when there are many, records are not annotated
+        let options = RtoolOptions {
+            file_headers: files.len() > 1,
+            with_filename: false,
+            line_numbers: false,
+            byte_offsets: false,
+            ..options
+        };
+        let line_processor = StatefulLineProcessor::new(Stats::new(measure));
+        let result = StatsProcessor {}.process_files_with_options(&line_processor, &files, std::io::stdout(), &(), &options);
+
+        if let Err(err) = result {
+            eprintln!("Could not process the files. The error: {}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    let json_keys = if matches.is_present(_STR_JSON) {
+        match matches.value_of(_STR_JSON) {
+            Some("numbers") => Some(JsonKeys::Numbers),
+            Some("header") => Some(JsonKeys::Header),
+            _ => Some(JsonKeys::None),
+        }
     } else {
-        files_it_opt.unwrap().collect()
+        None
     };
 
-    if field_mode {
-        let delim = matches.value_of(_STR_DELIMITER).unwrap();
-        let ranged_pairs_str = matches.value_of(_STR_FIELDS).unwrap();
-        let ranged_pairs = prepare_ranged_pairs(no_merge, ranged_pairs_str);
+    if matches.is_present(_STR_TABLE) && !field_mode {
+        eprintln!("The flag --table is dependent on the flag -d/--delimiter!");
+        std::process::exit(1);
+    }
+
+    if matches.is_present(_STR_STRICT_FIELDS) && !field_mode {
+        eprintln!("The flag --strict-fields is dependent on the flag -d/--delimiter!");
+        std::process::exit(1);
+    }
+
+    if matches.is_present(_STR_FILL_MISSING) && !field_mode {
+        eprintln!("The flag --fill-missing is dependent on the flag -d/--delimiter!");
+        std::process::exit(1);
+    }
+
+    let strict_fields = if matches.is_present(_STR_STRICT_FIELDS) {
+        match matches.value_of(_STR_STRICT_FIELDS).map(|count| (count, count.parse::<usize>())) {
+            Some((_, Ok(count))) if count > 0 => Some(FieldCount::Exactly(count)),
+            Some((count, _)) => {
+                eprintln!("The flag --strict-fields expects a number of fields, got `{}`!", count);
+                std::process::exit(1);
+            }
+            None => Some(FieldCount::First),
+        }
+    } else {
+        None
+    };
+
+    let table_window = matches.value_of(_STR_TABLE).map(|lines| match lines.parse::<usize>() {
+        Ok(lines) if lines > 0 => lines,
+        _ => {
+            eprintln!("The flag --table expects a number of lines, got `{}`!", lines);
+            std::process::exit(1);
+        }
+    });
+
+    // Without a window, rows are only written once all files end
+    if matches.is_present(_STR_TABLE) && table_window.is_none() && matches.is_present(_STR_FOLLOW) {
+        eprintln!("The flag --table expects a number of lines with --follow!");
+        std::process::exit(1);
+    }
+
+    let result = if layout_mode {
+        let delim = matches.value_of(_STR_OUTPUT_DELIMITER).unwrap_or("\t");
+        let columns = to_columns(
+            matches.value_of(_STR_LAYOUT),
+            matches.value_of(_STR_LAYOUT_FILE),
+            matches.value_of(_STR_COLUMNS),
+        );
+        let layout_processor = LayoutProcessor {};
+        let context = LayoutContext::new(&columns, delim, matches.is_present(_STR_TRIM));
+
+        match (json_keys, ascii_mode) {
+            (Some(keys), true) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(LayoutLineProcessor::new(ByteLineProcessor {}), keys));
+                layout_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (Some(keys), false) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(LayoutLineProcessor::new(CharUtf8LineProcessor {}), keys));
+                layout_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (None, true) => {
+                let line_processor = LayoutLineProcessor::new(ByteLineProcessor {});
+                layout_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (None, false) => {
+                let line_processor = LayoutLineProcessor::new(CharUtf8LineProcessor {});
+                layout_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+        }
+    } else if key_mode {
+        let delim = matches.value_of(_STR_OUTPUT_DELIMITER).unwrap_or("\t");
+        let key_paths = extract_key_paths(matches.value_of(_STR_KEYS).unwrap());
+        let key_processor = KeyProcessor {};
+        let context = KeyContext::new(&key_paths, delim);
+
+        match (json_keys, matches.is_present(_STR_LOGFMT)) {
+            (Some(keys), true) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(LogfmtLineProcessor {}, keys));
+                key_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (Some(keys), false) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(KeyJsonLineProcessor {}, keys));
+                key_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (None, true) => key_processor.process_to_output(&LogfmtLineProcessor {}, &files, &context, &options),
+            (None, false) => key_processor.process_to_output(&KeyJsonLineProcessor {}, &files, &context, &options),
+        }
+    } else if field_mode {
+        let delim = to_delimiter(matches.value_of(_STR_DELIMITER).unwrap(), &files, &options, verbose);
+        let (ranged_pairs_str, field_transforms) = extract_field_transforms(matches.value_of(_STR_FIELDS).unwrap())
+            .unwrap_or_else(|err| {
+                eprintln!("Invalid fields: {}!", err);
+                std::process::exit(1);
+            });
+        let ranged_pairs = prepare_ranged_pairs(no_merge, &ranged_pairs_str);
+        if matches.is_present(_STR_FILL_MISSING) {
+            let beyond_limit = |(start_pos, end_pos): &(usize, usize)| {
+                *start_pos > MAX_FILLED_FIELD || (*end_pos != usize::MAX && *end_pos > MAX_FILLED_FIELD)
+            };
+            if ranged_pairs.iter().any(beyond_limit) {
+                eprintln!("The flag --fill-missing fills in fields up to the field {}!", MAX_FILLED_FIELD);
+                std::process::exit(1);
+            }
+        }
         let field_processor = FieldProcessor {};
-        let context = FieldContext::new(&ranged_pairs, delim);
-        field_processor.process_to_stdout(&FieldUtf8LineProcessor {}, &files, &context);
+        let context = FieldContext::new(&ranged_pairs, &delim).with_transforms(&field_transforms);
+        let context = if matches.is_present(_STR_FILL_MISSING) {
+            context.with_fill_missing(matches.value_of(_STR_FILL_MISSING).unwrap_or(""))
+        } else {
+            context
+        };
+
+        if matches.is_present(_STR_TABLE) {
+            let line_processor = StatefulLineProcessor::new(TableLineProcessor::new(FieldUtf8LineProcessor {}, table_window));
+            // Rows of failed tables are not written
+            match strict_fields {
+                Some(field_count) => {
+                    let line_processor = StrictFieldLineProcessor::new(line_processor, field_count);
+                    field_processor.process_to_output(&line_processor, &files, &context, &options)
+                }
+                None => field_processor.process_to_output(&line_processor, &files, &context, &options),
+            }
+        } else {
+            match (json_keys, strict_fields) {
+                (Some(keys), Some(field_count)) => {
+                    let line_processor = StrictFieldLineProcessor::new(StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, keys)), field_count);
+                    field_processor.process_to_output(&line_processor, &files, &context, &options)
+                }
+                (Some(keys), None) => {
+                    let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(FieldUtf8LineProcessor {}, keys));
+                    field_processor.process_to_output(&line_processor, &files, &context, &options)
+                }
+                (None, Some(field_count)) => {
+                    let line_processor = StrictFieldLineProcessor::new(FieldUtf8LineProcessor {}, field_count);
+                    field_processor.process_to_output(&line_processor, &files, &context, &options)
+                }
+                (None, None) => field_processor.process_to_output(&FieldUtf8LineProcessor {}, &files, &context, &options),
+            }
+        }
     } else {
         let ranged_pairs_str = if char_mode {
             matches.value_of(_STR_CHARACTERS).unwrap()
@@ -161,12 +910,24 @@ pub fn do_rcut(input_args: &Vec<&str>) {
         let char_processor = CharProcessor {};
         let context = CharContext::new(&ranged_pairs);
 
-        if ascii_mode || byte_mode {
-            char_processor.process_to_stdout(&ByteLineProcessor {}, &files, &context);
-        } else {
-            char_processor.process_to_stdout(&CharUtf8LineProcessor {}, &files, &context);
+        match (json_keys, ascii_mode || byte_mode) {
+            (Some(keys), true) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(ByteLineProcessor {}, keys));
+                char_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (Some(keys), false) => {
+                let line_processor = StatefulLineProcessor::new(JsonLineProcessor::new(CharUtf8LineProcessor {}, keys));
+                char_processor.process_to_output(&line_processor, &files, &context, &options)
+            }
+            (None, true) => char_processor.process_to_output(&ByteLineProcessor {}, &files, &context, &options),
+            (None, false) => char_processor.process_to_output(&CharUtf8LineProcessor {}, &files, &context, &options),
         }
     };
+
+    if let Err(err) = result {
+        eprintln!("Could not process the files. The error: {}", err);
+        std::process::exit(1);
+    }
 }
 
 fn main() {
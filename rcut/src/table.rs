@@ -0,0 +1,124 @@
+//! Aligned table output, like `column -t`.
+//! Selected parts are kept as rows of cells and aligned by their display width
+//! when the table (or a window of it) is complete.
+//!
+
+use unicode_width::UnicodeWidthStr;
+
+use rcut_lib::format::SelectT;
+use rtools_traits::{RecordContext, StatefulProcessorT};
+
+/// Separator of columns in the aligned output
+const COLUMN_GAP: &str = "  ";
+
+/// Stateful processor aligning the selected parts into columns. Rows are buffered
+/// until the window is full, or until all files are processed without a window
+pub struct TableLineProcessor<S> {
+    selector: S,
+    window: Option<usize>,
+    rows: Vec<Vec<String>>,
+}
+
+impl<S> TableLineProcessor<S> {
+    pub fn new(selector: S, window: Option<usize>) -> TableLineProcessor<S> {
+        TableLineProcessor {
+            selector,
+            window,
+            rows: vec![],
+        }
+    }
+}
+
+impl<C, S: SelectT<C>> StatefulProcessorT<C> for TableLineProcessor<S> {
+    fn process_line(&mut self, line: &str, _record: &RecordContext, context: &C) -> Vec<u8> {
+        let cells = self
+            .selector
+            .select_parts(line, context)
+            .into_iter()
+            .map(|part| part.text.into_owned())
+            .collect();
+
+        self.rows.push(cells);
+
+        if self.window == Some(self.rows.len()) {
+            format_rows(self.rows.drain(..).collect()).into_bytes()
+        } else {
+            vec![]
+        }
+    }
+
+    /// Write the buffered rows
+    fn finish(&mut self, _context: &C) -> Vec<u8> {
+        format_rows(self.rows.drain(..).collect()).into_bytes()
+    }
+}
+
+/// Align the rows of cells into columns by their display width
+fn format_rows(rows: Vec<Vec<String>>) -> String {
+    let mut widths: Vec<usize> = vec![];
+    for row in &rows {
+        for (index, cell) in row.iter().enumerate() {
+            let width = UnicodeWidthStr::width(cell.as_str());
+            match widths.get_mut(index) {
+                Some(max_width) => *max_width = (*max_width).max(width),
+                None => widths.push(width),
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for row in rows {
+        for (index, cell) in row.iter().enumerate() {
+            out.push_str(cell);
+            // The last cell is not padded
+            if index + 1 < row.len() {
+                let padding = widths[index] - UnicodeWidthStr::width(cell.as_str());
+                out.push_str(&" ".repeat(padding));
+                out.push_str(COLUMN_GAP);
+            }
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcut_lib::{FieldContext, FieldUtf8LineProcessor};
+
+    fn table(lines: &[&str], window: Option<usize>) -> String {
+        let ranged_pairs = vec![(1, usize::MAX)];
+        let context = FieldContext::new(&ranged_pairs, ",");
+        let mut processor = TableLineProcessor::new(FieldUtf8LineProcessor {}, window);
+
+        let mut out = vec![];
+        for line in lines {
+            out.extend(processor.process_line(line, &RecordContext::default(), &context));
+        }
+        out.extend(processor.finish(&context));
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_table_aligns_by_display_width() {
+        let lines = ["id,name,city", "1,🐣,Hà Nội", "22,東京タワー,Tokyo"];
+        assert_eq!(
+            "id  name        city\n\
+             1   🐣          Hà Nội\n\
+             22  東京タワー  Tokyo\n",
+            table(&lines, None)
+        );
+    }
+
+    #[test]
+    fn test_table_windows_and_ragged_rows() {
+        assert_eq!("a     b\nlong  x  y\nc  d\n", table(&["a,b", "long,x,y", "c,d"], Some(2)));
+    }
+
+    #[test]
+    fn test_table_keeps_unit_separators_in_cells() {
+        assert_eq!("a   b\u{1f}c\ndd  e\n", table(&["a,b\u{1f}c", "dd,e"], None));
+    }
+}
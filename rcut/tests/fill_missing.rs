@@ -0,0 +1,37 @@
+//! Tests of `rcut --fill-missing`, run as a process with piped STDIN.
+//!
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run rcut with the arguments and STDIN
+fn rcut(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcut"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Rejected arguments exit before STDIN is read, closing the pipe early
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_fill_missing_fields() {
+    let output = rcut(&["-d", ",", "-f", "1,3-4", "--fill-missing=NA"], "a,b\n");
+    assert!(output.status.success());
+    assert_eq!("a,NA,NA\n", String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn test_fill_missing_rejects_huge_ranges() {
+    let output = rcut(&["-d", ",", "-f", "1-99999999999", "--fill-missing"], "a,b\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+
+    // Without filling in, huge ranges select the fields of the line
+    let output = rcut(&["-d", ",", "-f", "1-99999999999"], "a,b\n");
+    assert_eq!("a,b\n", String::from_utf8(output.stdout).unwrap());
+}
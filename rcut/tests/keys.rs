@@ -0,0 +1,39 @@
+//! Tests of the `rcut -k` wiring, run as a process with piped STDIN.
+//!
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run rcut with the arguments and STDIN
+fn rcut(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcut"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Rejected arguments exit before STDIN is read, closing the pipe early
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_keys_delimited_by_tab_or_output_delimiter() {
+    let line = "{\"a\":\"x\\ty\",\"b\":\"1,2\"}\n";
+
+    let output = rcut(&["-k", "a,b"], line);
+    assert!(output.status.success());
+    assert_eq!("x\\ty\t1,2\n", String::from_utf8(output.stdout).unwrap());
+
+    let output = rcut(&["-k", "a,b", "--output-delimiter", ","], line);
+    assert!(output.status.success());
+    assert_eq!("x\\ty,1\\,2\n", String::from_utf8(output.stdout).unwrap());
+}
+
+#[test]
+fn test_keys_reject_delimiter() {
+    let output = rcut(&["-k", "a", "-d", "auto"], "{\"a\":1}\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+}
@@ -0,0 +1,44 @@
+//! Tests of `rcut` reading STDIN through the file `-`, run as a process with piped STDIN.
+//!
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Run rcut with the arguments and STDIN, return its STDOUT
+fn rcut(args: &[&str], stdin: &str) -> String {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rcut"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_stdin_among_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.txt");
+    let b = dir.path().join("b.txt");
+    std::fs::write(&a, "a1\n").unwrap();
+    std::fs::write(&b, "b1\n").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    // STDIN is read where it is listed, given again it has no lines left
+    assert_eq!(
+        format!(
+            "==> {} <==\na1\n\n==> (standard input) <==\ns1\ns2\n\n==> {} <==\nb1\n\n==> (standard input) <==\n",
+            a, b
+        ),
+        rcut(&["-c", "1-2", "--file-headers", a, "-", b, "-"], "s1\ns2\n")
+    );
+}
+
+#[test]
+fn test_stdin_without_files() {
+    assert_eq!("s\nt\n", rcut(&["-c", "1"], "s1\nt2\n"));
+}
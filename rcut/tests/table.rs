@@ -0,0 +1,39 @@
+//! Tests of `rcut --table` written to output files, run as a process.
+//!
+
+use std::fs;
+use std::process::Command;
+
+/// Run rcut with the arguments, return whether it succeeded
+fn rcut(args: &[&str]) -> bool {
+    Command::new(env!("CARGO_BIN_EXE_rcut"))
+        .args(args)
+        .output()
+        .unwrap()
+        .status
+        .success()
+}
+
+#[test]
+fn test_table_to_output_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.csv");
+    let b = dir.path().join("b.csv");
+    let out = dir.path().join("out.txt");
+    fs::write(&a, "a,bb\nccc,d\n").unwrap();
+    fs::write(&b, "e,f\n").unwrap();
+    let (a, b) = (a.to_str().unwrap(), b.to_str().unwrap());
+
+    assert!(rcut(&["-d", ",", "-f", "1,2", "--table", "-o", out.to_str().unwrap(), a, b]));
+    assert_eq!("a    bb\nccc  d\ne    f\n", fs::read_to_string(&out).unwrap());
+
+    // Each file in place is a table of its own
+    assert!(rcut(&["-d", ",", "-f", "1,2", "--table", "--in-place", a, b]));
+    assert_eq!("a    bb\nccc  d\n", fs::read_to_string(a).unwrap());
+    assert_eq!("e  f\n", fs::read_to_string(b).unwrap());
+}
+
+#[test]
+fn test_table_without_window_rejects_follow() {
+    assert!(!rcut(&["-d", ",", "-f", "1", "--table", "--follow", "missing.csv"]));
+}
@@ -0,0 +1,232 @@
+//! `rpaste` is a Rust replacement for GNU paste that supports UTF-8.
+//! Implementation details are exported for reusability in case users
+//! are interested in building their own line mergers.
+//!
+
+use std::io::{self, BufRead, BufWriter, Write};
+
+extern crate rtools_traits;
+use rtools_traits::encoding::{self, EncodeWriter};
+use rtools_traits::{open_input, MultiLineProcessorT, MultiRtoolT, RecordReader, RtoolOptions};
+
+/// Cargo version specified in the Cargo.toml file
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Cargo version specified in the Cargo.toml file
+pub fn version() -> &'static str {
+    VERSION
+}
+
+/// Parse the list of delimiters like GNU paste: Each char is a delimiter, used in turn.
+/// The escapes `\n`, `\t`, `\\` and `\0` (no delimiter) are supported
+pub fn parse_delimiters(list: &str) -> Result<Vec<String>, String> {
+    let mut delims = vec![];
+    let mut chars = list.chars();
+
+    while let Some(c) = chars.next() {
+        let delim = match c {
+            '\\' => match chars.next() {
+                Some('n') => "\n".to_string(),
+                Some('t') => "\t".to_string(),
+                Some('0') => String::new(),
+                Some(c) => c.to_string(),
+                None => return Err(format!("the delimiter list `{}` ends with an unescaped backslash", list)),
+            },
+            c => c.to_string(),
+        };
+        delims.push(delim);
+    }
+
+    // An empty list pastes lines without delimiters
+    if delims.is_empty() {
+        delims.push(String::new());
+    }
+
+    Ok(delims)
+}
+
+pub trait PasteContextT {
+    /// Delimiters used in turn between the pasted lines
+    fn delims(&self) -> &[String];
+}
+
+pub struct PasteContext<'a> {
+    delims: &'a [String],
+}
+
+impl<'a> PasteContext<'a> {
+    pub fn new(delims: &'a [String]) -> PasteContext<'a> {
+        PasteContext { delims }
+    }
+}
+
+impl PasteContextT for PasteContext<'_> {
+    fn delims(&self) -> &[String] {
+        self.delims
+    }
+}
+
+/// Delimiter following the line of the given index, the delimiters are used in turn
+fn delim_after<C: PasteContextT>(index: usize, context: &C) -> &str {
+    let delims = context.delims();
+    &delims[index % delims.len()]
+}
+
+/// Paste the lines side by side. Lines of inputs past their end are `None` and pasted as empty
+pub fn paste_lines<C: PasteContextT>(lines: &[Option<&str>], context: &C) -> Vec<u8> {
+    let mut out_bytes: Vec<u8> = vec![];
+
+    for (index, line) in lines.iter().enumerate() {
+        if index > 0 {
+            out_bytes.extend(delim_after(index - 1, context).as_bytes());
+        }
+        out_bytes.extend(line.unwrap_or("").as_bytes());
+    }
+
+    out_bytes.push(b'\n');
+    out_bytes
+}
+
+/// Paste all lines of the input into one line
+pub fn paste_serial<R: BufRead, W: Write, C: PasteContextT>(
+    file: &str,
+    input: R,
+    output: &mut W,
+    context: &C,
+) -> io::Result<()> {
+    let mut reader = RecordReader::new(file, input);
+    let mut index = 0;
+
+    while let Some((line, _)) = reader.next_record()? {
+        if index > 0 {
+            output.write_all(delim_after(index - 1, context).as_bytes())?;
+        }
+        output.write_all(line.as_bytes())?;
+        index += 1;
+    }
+
+    output.write_all(b"\n")
+}
+
+pub struct PasteLineProcessor {}
+
+impl<C: PasteContextT> MultiLineProcessorT<C> for PasteLineProcessor {
+    /// Paste the lines read in lockstep side by side
+    fn process_lines(&self, lines: &[Option<&str>], context: &C) -> Vec<u8> {
+        paste_lines(lines, context)
+    }
+}
+
+/// Paster of files read in lockstep. The file `-` stands for STDIN,
+/// its lines are taken in turns when it is given many times
+pub struct PasteProcessor {}
+
+impl<C: PasteContextT, P: MultiLineProcessorT<C>> MultiRtoolT<C, P> for PasteProcessor {}
+
+/// Paste the lines of each file into one line per file. Like GNU paste, unreadable files
+/// are skipped and the other files pasted, then the unreadable files are returned as an error
+pub fn paste_files_serial<W: Write, C: PasteContextT>(
+    files: &[&str],
+    writable: W,
+    context: &C,
+    options: &RtoolOptions,
+) -> io::Result<()> {
+    let mut output = BufWriter::new(EncodeWriter::new(writable, options.output_encoding));
+    let mut errors: Vec<io::Error> = vec![];
+
+    for file in files {
+        match open_input(file, options) {
+            Ok(input) => paste_serial(file, input, &mut output, context)?,
+            Err(err) => errors.push(io::Error::new(err.kind(), format!("`{}`: {}", file, err))),
+        }
+    }
+
+    encoding::finish_output(output)?;
+
+    match errors.len() {
+        0 => Ok(()),
+        1 => Err(errors.remove(0)),
+        _ => Err(io::Error::new(
+            errors[0].kind(),
+            errors.iter().map(io::Error::to_string).collect::<Vec<_>>().join(", "),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtools_traits::LockstepReader;
+    use std::io::Cursor;
+
+    fn delims(list: &str) -> Vec<String> {
+        parse_delimiters(list).unwrap()
+    }
+
+    #[test]
+    fn test_parse_delimiters() {
+        assert_eq!(vec![",", "🐣"], delims(",🐣"));
+        assert_eq!(vec!["\t", "\n", "\\", "", "x"], delims("\\t\\n\\\\\\0\\x"));
+        assert_eq!(vec![""], delims(""));
+        assert!(parse_delimiters("a\\").is_err());
+    }
+
+    #[test]
+    fn test_paste_lines() {
+        let delims = delims(",;");
+        let context = PasteContext::new(&delims);
+
+        assert_eq!(
+            "a,🐣;c,d\n".as_bytes(),
+            &paste_lines(&[Some("a"), Some("🐣"), Some("c"), Some("d")], &context)[..]
+        );
+        assert_eq!(",b;\n".as_bytes(), &paste_lines(&[None, Some("b"), None], &context)[..]);
+    }
+
+    #[test]
+    fn test_paste_serial() {
+        let delims = delims("\\t,");
+        let context = PasteContext::new(&delims);
+        let mut out = vec![];
+
+        paste_serial("", Cursor::new("a\r\nb\nc\nd"), &mut out, &context).unwrap();
+        paste_serial("", Cursor::new(""), &mut out, &context).unwrap();
+        assert_eq!("a\tb,c\td\n\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_paste_in_lockstep() {
+        let reader = LockstepReader::new(vec![
+            ("a", Box::new(Cursor::new("a1\na2\na3\n"))),
+            ("b", Box::new(Cursor::new("b1\n"))),
+        ]);
+        let delims = delims("\\t");
+        let context = PasteContext::new(&delims);
+
+        let mut out = vec![];
+        PasteProcessor {}
+            .process_lockstep(&PasteLineProcessor {}, reader, &mut out, &context, &RtoolOptions::default())
+            .unwrap();
+        assert_eq!("a1\tb1\na2\t\na3\t\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_paste_files_serial_with_unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.txt");
+        std::fs::write(&a, "a1\na2\n").unwrap();
+        let files = vec!["no-such-file", a.to_str().unwrap(), "no-such-file-2"];
+        let delims = delims(",");
+        let context = PasteContext::new(&delims);
+
+        // The readable files are pasted nonetheless
+        let mut out = vec![];
+        let err = paste_files_serial(&files, &mut out, &context, &RtoolOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!("a1,a2\n", String::from_utf8(out).unwrap());
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        assert!(err.to_string().starts_with("`no-such-file`: "));
+        assert!(err.to_string().contains(", `no-such-file-2`: "));
+    }
+}
@@ -0,0 +1,104 @@
+extern crate clap;
+use clap::{App, Arg};
+
+extern crate rtools_traits;
+use rtools_traits::{MultiRtoolT, RtoolOptions, STDIN_FILE_NAME};
+
+extern crate rpaste_lib;
+use rpaste_lib::{
+    paste_files_serial, parse_delimiters, version, PasteContext, PasteLineProcessor, PasteProcessor,
+};
+
+/// Perform operations similar to GNU paste
+pub fn do_rpaste(input_args: &Vec<&str>) {
+    const _STR_DELIMITERS: &str = "delimiters";
+    const _STR_SERIAL: &str = "serial";
+    const _STR_DECOMPRESS: &str = "decompress";
+
+    let matches = App::new("rpaste")
+        .version(version())
+        .about("Replacement for GNU paste. Written in Rust and supports UTF-8.")
+        .author("Viet Le")
+        .arg(
+            Arg::with_name(_STR_DELIMITERS)
+                .short("d")
+                .long(_STR_DELIMITERS)
+                .value_name("LIST")
+                .help(
+                    "Use chars from LIST in turn instead of TAB between lines.\n\
+                    The escapes \\n \\t \\\\ and \\0 (no delimiter) are supported.\n\
+                    E.g. -d ',;'",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name(_STR_SERIAL)
+                .short("s")
+                .long(_STR_SERIAL)
+                .help("Paste the lines of one file at a time instead of in parallel.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_DECOMPRESS)
+                .long(_STR_DECOMPRESS)
+                .help(
+                    "Decompress inputs compressed with gzip/zstd/xz/bzip2.\n\
+                    Compression is auto-detected by magic bytes.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("files")
+                .help(
+                    "The content of these files will be used.\n\
+                     If no files given, STDIN will be used.\n\
+                     The file - stands for STDIN, its lines are taken\n\
+                     in turns when it is given many times.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .multiple(true),
+        )
+        .get_matches_from(input_args);
+
+    let delims = parse_delimiters(matches.value_of(_STR_DELIMITERS).unwrap_or("\\t")).unwrap_or_else(|err| {
+        eprintln!("Invalid delimiters: {}!", err);
+        std::process::exit(1);
+    });
+    let context = PasteContext::new(&delims);
+
+    let options = RtoolOptions {
+        decompress: matches.is_present(_STR_DECOMPRESS),
+        ..Default::default()
+    };
+
+    // NOTE: Use `values_of` instead of `value_of`!!!!
+    let files = match matches.values_of("files") {
+        Some(files_it) => files_it.collect(),
+        None => vec![STDIN_FILE_NAME],
+    };
+
+    let result = if matches.is_present(_STR_SERIAL) {
+        paste_files_serial(&files, std::io::stdout(), &context, &options)
+    } else {
+        PasteProcessor {}.process_files_in_lockstep(&PasteLineProcessor {}, &files, std::io::stdout(), &context, &options)
+    };
+
+    if let Err(err) = result {
+        eprintln!("Could not process the files. The error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let input_args: Vec<_> = std::env::args().collect();
+    let input_args = input_args.iter().map(|s| s.as_str()).collect();
+
+    do_rpaste(&input_args)
+}
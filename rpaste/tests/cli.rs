@@ -0,0 +1,63 @@
+//! Tests of the `rpaste` options, run as a process with piped STDIN.
+//!
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run rpaste with the arguments and STDIN
+fn rpaste(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rpaste"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    // Unreadable files and rejected arguments fail before STDIN is read, closing the pipe early
+    let _ = child.stdin.take().unwrap().write_all(stdin.as_bytes());
+    child.wait_with_output().unwrap()
+}
+
+fn stdout(output: Output) -> String {
+    assert!(output.status.success());
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn test_paste_with_delimiters() {
+    let dir = tempfile::tempdir().unwrap();
+    let a = dir.path().join("a.txt");
+    std::fs::write(&a, "a1\na2\n").unwrap();
+    let a = a.to_str().unwrap();
+
+    assert_eq!("a1\ts1\na2\ts2\n", stdout(rpaste(&[a, "-"], "s1\ns2\n")));
+    assert_eq!("a1,s1;a1\na2,s2;a2\n", stdout(rpaste(&["-d", ",;", a, "-", a], "s1\ns2\n")));
+    // STDIN given many times is read in turns
+    assert_eq!("s1,s2\ns3,\n", stdout(rpaste(&["-d", ",", "-", "-"], "s1\ns2\ns3\n")));
+}
+
+#[test]
+fn test_paste_serial() {
+    assert_eq!("s1\ts2\ts3\n", stdout(rpaste(&["-s"], "s1\ns2\ns3\n")));
+    assert_eq!("s1,s2|s3\n", stdout(rpaste(&["-s", "-d", ",|"], "s1\ns2\ns3\n")));
+}
+
+#[test]
+fn test_paste_fails_on_unreadable_files() {
+    let output = rpaste(&["no-such-file", "-"], "s1\n");
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("`no-such-file`: "));
+
+    // Serial pasting pastes the readable files first
+    let output = rpaste(&["-s", "no-such-file", "-"], "s1\ns2\n");
+    assert!(!output.status.success());
+    assert_eq!("s1\ts2\n", String::from_utf8(output.stdout).unwrap());
+    assert!(String::from_utf8(output.stderr).unwrap().contains("`no-such-file`: "));
+}
+
+#[test]
+fn test_paste_rejects_trailing_backslash() {
+    let output = rpaste(&["-d", "a\\", "-"], "s1\n");
+    assert!(!output.status.success());
+}
@@ -0,0 +1,74 @@
+//! Async counterpart of `RtoolT` for embedding line processors
+//! into Tokio-based services. Enabled by the `async` cargo feature.
+//!
+
+use std::future::Future;
+use std::io;
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+
+use crate::record::{strip_line_ending, RecordContext};
+use crate::LineProcessorT;
+
+pub trait AsyncRtoolT<C, P: LineProcessorT<C>> {
+    /// Generic async line processor that delegates to concrete line processors.
+    /// The input is one of many, so the line processor does not finish
+    fn process_lines_async<R, W>(
+        &self,
+        line_processor: &P,
+        mut input: R,
+        output: &mut W,
+        context: &C,
+    ) -> impl Future<Output = io::Result<()>> + Send
+    where
+        Self: Sync,
+        P: Sync,
+        C: Sync,
+        R: AsyncBufRead + Unpin + Send,
+        W: AsyncWrite + Unpin + Send,
+    {
+        async move {
+            let mut line = String::new();
+            let mut record = RecordContext::default();
+            output.write_all(&line_processor.begin_file("", context)).await?;
+
+            loop {
+                line.clear();
+                let byte_len = input.read_line(&mut line).await?;
+                if byte_len == 0 {
+                    break;
+                }
+
+                record.line_number += 1;
+                record.byte_len = byte_len;
+                strip_line_ending(&mut line);
+
+                let out_bytes = line_processor.try_process_record(&line, &record, context)?;
+                output.write_all(&out_bytes).await?;
+
+                record.byte_offset += byte_len as u64;
+            }
+
+            output.write_all(&line_processor.end_file("", context)).await?;
+            output.flush().await
+        }
+    }
+
+    /// Process async readable object: Buffer it and send input to the line processor
+    fn process_readable_async<R, W>(
+        &self,
+        line_processor: &P,
+        input: R,
+        output: &mut W,
+        context: &C,
+    ) -> impl Future<Output = io::Result<()>> + Send
+    where
+        Self: Sync,
+        P: Sync,
+        C: Sync,
+        R: AsyncRead + Unpin + Send,
+        W: AsyncWrite + Unpin + Send,
+    {
+        self.process_lines_async(line_processor, BufReader::new(input), output, context)
+    }
+}
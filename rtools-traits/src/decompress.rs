@@ -0,0 +1,248 @@
+//! Transparent decompression of inputs detected by their magic bytes.
+//! Each format is backed by an optional cargo feature:
+//! `gzip`, `zstd`, `xz` and `bzip2` (or `decompress` for all of them).
+//!
+
+use std::io::{self, BufRead, Cursor, Read};
+
+#[cfg(any(feature = "gzip", feature = "zstd", feature = "xz", feature = "bzip2"))]
+use std::io::BufReader;
+
+/// Compression formats recognised by their magic bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    /// Name of the cargo feature enabling the format
+    pub fn feature_name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+            Compression::Xz => "xz",
+            Compression::Bzip2 => "bzip2",
+        }
+    }
+}
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+const XZ_MAGIC: &[u8] = &[0xfd, b'7', b'z', b'X', b'Z', 0x00];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+/// Magic of the first block of a bzip2 stream, or of its end if the stream is empty
+const BZIP2_BLOCK_MAGICS: [&[u8]; 2] = [b"1AY&SY", b"\x17rE8P\x90"];
+/// Length of a bzip2 stream header: magic, block size digit `1` to `9` and block magic
+const BZIP2_HEADER_LEN: usize = 10;
+
+/// Whether the header starts like a bzip2 stream header, as far as it goes
+fn is_bzip2_prefix(header: &[u8]) -> bool {
+    let header = &header[..header.len().min(BZIP2_HEADER_LEN)];
+
+    header.iter().zip(BZIP2_MAGIC).all(|(byte, magic)| byte == magic)
+        && header.get(3).is_none_or(|size| (b'1'..=b'9').contains(size))
+        && BZIP2_BLOCK_MAGICS
+            .iter()
+            .any(|magic| header.iter().skip(4).zip(magic.iter()).all(|(byte, magic)| byte == magic))
+}
+
+/// Whether more bytes are needed to tell if the header is compressed
+fn is_partial_magic(header: &[u8]) -> bool {
+    [GZIP_MAGIC, ZSTD_MAGIC, XZ_MAGIC]
+        .iter()
+        .any(|magic| header.len() < magic.len() && magic.starts_with(header))
+        || (header.len() < BZIP2_HEADER_LEN && is_bzip2_prefix(header))
+}
+
+/// Detect the compression format from the leading bytes of an input.
+/// bzip2 needs its full stream header, so that text starting with `BZh` stays plain
+pub fn detect_compression(header: &[u8]) -> Option<Compression> {
+    if header.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if header.starts_with(ZSTD_MAGIC) {
+        Some(Compression::Zstd)
+    } else if header.starts_with(XZ_MAGIC) {
+        Some(Compression::Xz)
+    } else if header.len() >= BZIP2_HEADER_LEN && is_bzip2_prefix(header) {
+        Some(Compression::Bzip2)
+    } else {
+        None
+    }
+}
+
+/// Read the leading bytes of the input until its compression can be detected,
+/// e.g. across the short reads of pipes. Stops at the first byte that is no magic
+pub fn read_header<R: Read>(mut input: R) -> io::Result<Vec<u8>> {
+    let mut header = vec![];
+    let mut byte = [0; 1];
+
+    while is_partial_magic(&header) && input.read(&mut byte)? > 0 {
+        header.push(byte[0]);
+    }
+
+    Ok(header)
+}
+
+/// Peek at the input and wrap it into a decoder if it is compressed.
+/// Uncompressed inputs are returned untouched
+pub fn decompress<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    if !is_partial_magic(input.fill_buf()?) {
+        let compression = detect_compression(input.fill_buf()?);
+        return wrap_decoder(input, compression);
+    }
+
+    // The buffered bytes are too few to tell: They are read ahead and put back
+    let header = read_header(&mut input)?;
+    let compression = detect_compression(&header);
+    wrap_decoder(Cursor::new(header).chain(input), compression)
+}
+
+fn wrap_decoder<'a, R: BufRead + 'a>(input: R, compression: Option<Compression>) -> io::Result<Box<dyn BufRead + 'a>> {
+    match compression {
+        None => Ok(Box::new(input)),
+        #[cfg(feature = "gzip")]
+        Some(Compression::Gzip) => Ok(Box::new(BufReader::new(
+            flate2::bufread::MultiGzDecoder::new(input),
+        ))),
+        #[cfg(feature = "zstd")]
+        Some(Compression::Zstd) => Ok(Box::new(BufReader::new(
+            zstd::stream::read::Decoder::with_buffer(input)?,
+        ))),
+        #[cfg(feature = "xz")]
+        Some(Compression::Xz) => Ok(Box::new(BufReader::new(
+            xz2::bufread::XzDecoder::new_multi_decoder(input),
+        ))),
+        #[cfg(feature = "bzip2")]
+        Some(Compression::Bzip2) => Ok(Box::new(BufReader::new(
+            bzip2::bufread::MultiBzDecoder::new(input),
+        ))),
+        #[allow(unreachable_patterns)]
+        Some(compression) => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "the input is {:?} compressed but the `{}` feature is not enabled",
+                compression,
+                compression.feature_name()
+            ),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const _STR_LINES: &str = "🦃🐔🐓\nabc:def\n";
+
+    fn read_all(mut input: Box<dyn BufRead + '_>) -> String {
+        let mut out = String::new();
+        input.read_to_string(&mut out).unwrap();
+        out
+    }
+
+    #[test]
+    fn test_detect_compression() {
+        assert_eq!(
+            Some(Compression::Gzip),
+            detect_compression(&[0x1f, 0x8b, 0x08])
+        );
+        assert_eq!(
+            Some(Compression::Zstd),
+            detect_compression(&[0x28, 0xb5, 0x2f, 0xfd, 0])
+        );
+        assert_eq!(
+            Some(Compression::Xz),
+            detect_compression(b"\xfd7zXZ\x00\x00")
+        );
+        assert_eq!(Some(Compression::Bzip2), detect_compression(b"BZh91AY&SY"));
+        assert_eq!(Some(Compression::Bzip2), detect_compression(b"BZh1\x17rE8P\x90"));
+        assert_eq!(None, detect_compression(b"BZh,x\n"));
+        assert_eq!(None, detect_compression(b"BZh01AY&SY"));
+        assert_eq!(None, detect_compression(b"BZh91AY"));
+        assert_eq!(None, detect_compression(b"plain text"));
+        assert_eq!(None, detect_compression(b""));
+    }
+
+    #[test]
+    fn test_decompress_plain_input() {
+        let input = Cursor::new(_STR_LINES.as_bytes());
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[test]
+    fn test_decompress_text_starting_like_bzip2() {
+        // Reads of one byte at a time, like short reads of pipes
+        let input = io::BufReader::with_capacity(1, Cursor::new("BZh,x\nBZh91AY&SY\n"));
+        assert_eq!("BZh,x\nBZh91AY&SY\n", read_all(decompress(input).unwrap()));
+    }
+
+    #[test]
+    fn test_read_header() {
+        assert_eq!(b"p".to_vec(), read_header(Cursor::new("plain")).unwrap());
+        assert_eq!(b"BZh9".to_vec(), read_header(Cursor::new("BZh9")).unwrap());
+        assert_eq!(b"BZh91AY&SY".to_vec(), read_header(Cursor::new("BZh91AY&SY...")).unwrap());
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decompress_gzip_in_short_reads() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(_STR_LINES.as_bytes()).unwrap();
+        let input = io::BufReader::with_capacity(1, Cursor::new(encoder.finish().unwrap()));
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_decompress_gzip() {
+        use std::io::Write;
+
+        let mut encoder = flate2::write::GzEncoder::new(vec![], flate2::Compression::default());
+        encoder.write_all(_STR_LINES.as_bytes()).unwrap();
+        let input = Cursor::new(encoder.finish().unwrap());
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_decompress_zstd() {
+        let compressed = zstd::stream::encode_all(_STR_LINES.as_bytes(), 0).unwrap();
+        let input = Cursor::new(compressed);
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[cfg(feature = "xz")]
+    #[test]
+    fn test_decompress_xz() {
+        let mut compressed = vec![];
+        xz2::read::XzEncoder::new(_STR_LINES.as_bytes(), 6)
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let input = Cursor::new(compressed);
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[cfg(feature = "bzip2")]
+    #[test]
+    fn test_decompress_bzip2() {
+        let mut compressed = vec![];
+        bzip2::read::BzEncoder::new(_STR_LINES.as_bytes(), bzip2::Compression::default())
+            .read_to_end(&mut compressed)
+            .unwrap();
+        let input = Cursor::new(compressed);
+        assert_eq!(_STR_LINES, read_all(decompress(input).unwrap()));
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn test_decompress_gzip_without_feature() {
+        let input = Cursor::new(vec![0x1f, 0x8b, 0x08, 0x00]);
+        let err = decompress(input).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+}
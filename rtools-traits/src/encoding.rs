@@ -0,0 +1,319 @@
+//! Input decoding into UTF-8 and output re-encoding.
+//! Encodings are looked up by their WHATWG labels, e.g. `utf-16le`,
+//! `shift_jis`, `gbk` or `windows-1252`.
+//!
+
+use std::io::{self, BufRead, BufReader, BufWriter, Cursor, Read, Write};
+use std::str;
+
+pub use encoding_rs::Encoding;
+use encoding_rs::{Encoder, UTF_16BE, UTF_16LE, UTF_8};
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+/// Look up an encoding by its label. Labels are case-insensitive
+pub fn encoding_for_label(label: &str) -> Option<&'static Encoding> {
+    Encoding::for_label(label.trim().as_bytes())
+}
+
+/// Decode the input into UTF-8. A BOM, if present, takes precedence over the given encoding
+pub fn decode_input<'a, R: BufRead + 'a>(
+    input: R,
+    encoding: &'static Encoding,
+) -> Box<dyn BufRead + 'a> {
+    Box::new(BufReader::new(
+        DecodeReaderBytesBuilder::new()
+            .encoding(Some(encoding))
+            .bom_override(true)
+            .build(input),
+    ))
+}
+
+const UTF_8_BOM: &[u8] = b"\xef\xbb\xbf";
+const UTF_16LE_BOM: &[u8] = b"\xff\xfe";
+const UTF_16BE_BOM: &[u8] = b"\xfe\xff";
+
+/// Strip the UTF-8 BOM of the input, or decode it into UTF-8 if it starts with a UTF-16 BOM.
+/// Inputs without BOM are returned untouched
+pub fn sniff_bom<'a, R: BufRead + 'a>(mut input: R) -> io::Result<Box<dyn BufRead + 'a>> {
+    let boms = [UTF_8_BOM, UTF_16LE_BOM, UTF_16BE_BOM];
+    let mut header = vec![];
+    let mut byte = [0; 1];
+
+    // Read on while the header may still be a BOM, e.g. across the short reads of pipes
+    while boms.iter().any(|bom| bom.len() > header.len() && bom.starts_with(&header))
+        && input.read(&mut byte)? > 0
+    {
+        header.push(byte[0]);
+    }
+
+    if header == UTF_8_BOM {
+        return Ok(Box::new(input));
+    }
+
+    let utf16 = header.starts_with(UTF_16LE_BOM) || header.starts_with(UTF_16BE_BOM);
+    let input = Cursor::new(header).chain(input);
+    if utf16 {
+        // The BOM takes precedence over the encoding
+        Ok(decode_input(input, UTF_16LE))
+    } else {
+        Ok(Box::new(input))
+    }
+}
+
+/// Finalize the encoder under the buffered output and flush it
+pub fn finish_output<W: Write>(output: BufWriter<EncodeWriter<W>>) -> io::Result<()> {
+    output.into_inner().map_err(|err| err.into_error())?.finish()
+}
+
+/// Writer that re-encodes UTF-8 bytes into the target encoding.
+/// Characters that cannot be mapped are written as HTML numeric character references
+pub struct EncodeWriter<W: Write> {
+    inner: W,
+    encoding: Option<&'static Encoding>,
+    encoder: Option<Encoder>,
+    // Incomplete UTF-8 sequence left over from the previous write
+    pending: Vec<u8>,
+}
+
+impl<W: Write> EncodeWriter<W> {
+    /// Wrap the writer. Without encoding or with UTF-8, bytes are passed through
+    pub fn new(inner: W, encoding: Option<&'static Encoding>) -> EncodeWriter<W> {
+        let encoding = encoding.filter(|encoding| *encoding != UTF_8);
+        let encoder = match encoding {
+            Some(encoding) if encoding != UTF_16LE && encoding != UTF_16BE => {
+                Some(encoding.new_encoder())
+            }
+            _ => None,
+        };
+
+        EncodeWriter {
+            inner,
+            encoding,
+            encoder,
+            pending: vec![],
+        }
+    }
+
+    /// Encode the text. The last text finalizes the encoder, e.g. returning to ASCII
+    /// for stateful encodings like ISO-2022-JP, and the next text starts a new encoder
+    fn write_str(&mut self, text: &str, last: bool) -> io::Result<()> {
+        let mut out_bytes: Vec<u8> = Vec::with_capacity(text.len() * 2);
+
+        if self.encoding == Some(UTF_16LE) {
+            text.encode_utf16()
+                .for_each(|unit| out_bytes.extend(&unit.to_le_bytes()));
+        } else if self.encoding == Some(UTF_16BE) {
+            text.encode_utf16()
+                .for_each(|unit| out_bytes.extend(&unit.to_be_bytes()));
+        } else if let Some(encoder) = self.encoder.as_mut() {
+            // `encode_from_utf8_to_vec` only fills the spare capacity
+            let mut src = text;
+            loop {
+                out_bytes.reserve(text.len() + 16);
+                let (result, read, _) = encoder.encode_from_utf8_to_vec(src, &mut out_bytes, last);
+                src = &src[read..];
+                if result == encoding_rs::CoderResult::InputEmpty {
+                    break;
+                }
+            }
+
+            if last {
+                *encoder = encoder.encoding().new_encoder();
+            }
+        }
+
+        self.inner.write_all(&out_bytes)
+    }
+
+    /// Finalize the encoder, e.g. returning to ASCII for stateful encodings, and flush.
+    /// Fails on an incomplete UTF-8 sequence left over. Writing again starts a new encoder
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.encoding.is_some() {
+            let pending = std::mem::take(&mut self.pending);
+            if !pending.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("incomplete UTF-8 sequence {:x?} at the end of the output", pending),
+                ));
+            }
+            self.write_str("", true)?;
+        }
+
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Write for EncodeWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.encoding.is_none() {
+            return self.inner.write(buf);
+        }
+
+        self.pending.extend_from_slice(buf);
+        let pending = std::mem::take(&mut self.pending);
+
+        let valid_up_to = match str::from_utf8(&pending) {
+            Ok(_) => pending.len(),
+            Err(err) if err.error_len().is_none() => err.valid_up_to(),
+            Err(err) => {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, err));
+            }
+        };
+
+        // The prefix has just been validated
+        let text = str::from_utf8(&pending[..valid_up_to]).unwrap();
+        self.write_str(text, false)?;
+        self.pending = pending[valid_up_to..].to_vec();
+
+        Ok(buf.len())
+    }
+
+    /// Flush the bytes encoded so far. An incomplete UTF-8 sequence is kept for the next write
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+impl<W: Write> Drop for EncodeWriter<W> {
+    /// Finalize the encoder if the writer was not finished. Errors are ignored, like `BufWriter` does
+    fn drop(&mut self) {
+        let _ = self.finish();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(bytes: &[u8], label: &str) -> String {
+        let mut out = String::new();
+        decode_input(Cursor::new(bytes), encoding_for_label(label).unwrap())
+            .read_to_string(&mut out)
+            .unwrap();
+        out
+    }
+
+    fn encode(text: &str, label: &str, chunk_size: usize) -> Vec<u8> {
+        let mut writer = EncodeWriter::new(vec![], encoding_for_label(label));
+        for chunk in text.as_bytes().chunks(chunk_size) {
+            writer.write_all(chunk).unwrap();
+        }
+        writer.finish().unwrap();
+        writer.inner.clone()
+    }
+
+    #[test]
+    fn test_encoding_for_label() {
+        assert_eq!(Some(UTF_16LE), encoding_for_label("UTF-16LE"));
+        assert_eq!(Some(encoding_rs::SHIFT_JIS), encoding_for_label("sjis"));
+        assert_eq!(Some(encoding_rs::GBK), encoding_for_label(" gbk "));
+        assert_eq!(None, encoding_for_label("no-such-encoding"));
+    }
+
+    #[test]
+    fn test_decode_utf16le_with_bom() {
+        let bytes = [0xff, 0xfe, b'a', 0, b',', 0, b'b', 0, b'\n', 0];
+        // The BOM wins over the requested encoding
+        assert_eq!("a,b\n", decode(&bytes, "windows-1252"));
+    }
+
+    #[test]
+    fn test_decode_utf16le_without_bom() {
+        let bytes = [0x3d, 0xd8, 0x23, 0xdc, b'\n', 0];
+        assert_eq!("🐣\n", decode(&bytes, "utf-16le"));
+    }
+
+    #[test]
+    fn test_decode_shift_jis() {
+        let bytes = [0x93, 0xfa, 0x96, 0x7b, b',', b'1', b'\n'];
+        assert_eq!("日本,1\n", decode(&bytes, "shift_jis"));
+    }
+
+    #[test]
+    fn test_encode_passthrough() {
+        assert_eq!("🐣,a\n".as_bytes().to_vec(), encode("🐣,a\n", "utf-8", 1));
+    }
+
+    #[test]
+    fn test_encode_utf16le_split_chars() {
+        assert_eq!(
+            vec![0x3d, 0xd8, 0x23, 0xdc, b'\n', 0],
+            encode("🐣\n", "utf-16le", 1)
+        );
+    }
+
+    #[test]
+    fn test_encode_gbk() {
+        assert_eq!(
+            vec![0xc4, 0xe3, 0xba, 0xc3, b'\n'],
+            encode("你好\n", "gbk", 2)
+        );
+    }
+
+    #[test]
+    fn test_encode_unmappable_char() {
+        assert_eq!(b"a&#128035;\n".to_vec(), encode("a🐣\n", "windows-1252", 3));
+    }
+
+    #[test]
+    fn test_encode_finalizes_stateful_encoding() {
+        // ISO-2022-JP returns to ASCII with `ESC ( B` at the end of the output
+        assert_eq!(
+            b"\x1b$BF|\x1b(B\n\x1b$BK\\\x1b(B".to_vec(),
+            encode("日\n本", "iso-2022-jp", 1)
+        );
+    }
+
+    #[test]
+    fn test_encode_incomplete_utf8_at_the_end() {
+        let mut writer = EncodeWriter::new(vec![], Some(encoding_rs::GBK));
+        writer.write_all(&"a你".as_bytes()[..3]).unwrap();
+        // Flushing keeps the incomplete sequence for the next write
+        writer.flush().unwrap();
+        assert_eq!(b"a".to_vec(), writer.inner);
+
+        let err = writer.finish().err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(b"a".to_vec(), writer.inner);
+    }
+
+    #[test]
+    fn test_encode_flush_between_split_chars() {
+        let mut writer = EncodeWriter::new(vec![], encoding_for_label("iso-2022-jp"));
+        for byte in "日本\n".as_bytes() {
+            writer.write_all(&[*byte]).unwrap();
+            writer.flush().unwrap();
+        }
+        writer.finish().unwrap();
+        assert_eq!(b"\x1b$BF|K\\\x1b(B\n".to_vec(), writer.inner);
+    }
+
+    fn sniff(bytes: &[u8]) -> Vec<u8> {
+        let mut out = vec![];
+        // Reads of one byte at a time, like short reads of pipes
+        sniff_bom(BufReader::with_capacity(1, Cursor::new(bytes)))
+            .unwrap()
+            .read_to_end(&mut out)
+            .unwrap();
+        out
+    }
+
+    #[test]
+    fn test_sniff_bom() {
+        assert_eq!(b"a,b\n".to_vec(), sniff(b"\xef\xbb\xbfa,b\n"));
+        assert_eq!(b"a\n".to_vec(), sniff(&[0xff, 0xfe, b'a', 0, b'\n', 0]));
+        assert_eq!(b"a\n".to_vec(), sniff(&[0xfe, 0xff, 0, b'a', 0, b'\n']));
+        // Only the first BOM is stripped, partial BOMs are kept
+        assert_eq!(b"\xef\xbb\xbf".to_vec(), sniff(b"\xef\xbb\xbf\xef\xbb\xbf"));
+        assert_eq!(b"\xef\xbbx".to_vec(), sniff(b"\xef\xbbx"));
+        assert_eq!(b"".to_vec(), sniff(b""));
+        assert_eq!(b"plain".to_vec(), sniff(b"plain"));
+    }
+
+    #[test]
+    fn test_encode_invalid_utf8() {
+        let mut writer = EncodeWriter::new(vec![], Some(encoding_rs::GBK));
+        let err = writer.write(&[b'a', 0xff, b'b']).err().unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+}
@@ -0,0 +1,139 @@
+//! Filtering of records before they reach the line processor.
+//!
+
+use std::io;
+
+pub use regex::Regex;
+
+use crate::record::decode_line;
+use crate::RecordContext;
+
+/// Pattern that records are matched against
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// Records containing the text
+    Literal(String),
+    /// Records matching the regular expression
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn is_match(&self, line: &str) -> bool {
+        match self {
+            Pattern::Literal(text) => line.contains(text.as_str()),
+            Pattern::Regex(regex) => regex.is_match(line),
+        }
+    }
+}
+
+/// Selection of records by line numbers and patterns. Line numbers count within each file
+#[derive(Clone, Debug, Default)]
+pub struct RecordFilter {
+    /// Skip this many lines at the start of each file, e.g. headers
+    pub skip_lines: u64,
+    /// Stop reading each file after this line number
+    pub stop_after: Option<u64>,
+    /// Keep only records matching the pattern
+    pub pattern: Option<Pattern>,
+    /// Keep only records not matching the pattern instead
+    pub invert_match: bool,
+}
+
+impl RecordFilter {
+    /// Whether all records are kept
+    pub fn keeps_all(&self) -> bool {
+        self.skip_lines == 0 && self.stop_after.is_none() && self.pattern.is_none()
+    }
+
+    /// Whether the record and all records after it in the file are filtered out
+    pub fn is_past_end(&self, record: &RecordContext) -> bool {
+        match self.stop_after {
+            Some(stop_after) => record.line_number > stop_after,
+            None => false,
+        }
+    }
+
+    /// Whether the record is kept
+    pub fn accepts(&self, line: &str, record: &RecordContext) -> bool {
+        if record.line_number <= self.skip_lines || self.is_past_end(record) {
+            return false;
+        }
+
+        match &self.pattern {
+            Some(pattern) => pattern.is_match(line) != self.invert_match,
+            None => true,
+        }
+    }
+
+    /// Whether the raw record is kept. It is decoded only to be matched against the pattern
+    pub fn accepts_raw(&self, raw: &[u8], record: &RecordContext) -> io::Result<bool> {
+        match &self.pattern {
+            Some(_) => Ok(self.accepts(decode_line(raw)?, record)),
+            None => Ok(self.accepts("", record)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kept_lines(filter: &RecordFilter, lines: &[&str]) -> Vec<u64> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(index, line)| {
+                let record = RecordContext {
+                    line_number: *index as u64 + 1,
+                    ..Default::default()
+                };
+                filter.accepts(line, &record)
+            })
+            .map(|(index, _)| index as u64 + 1)
+            .collect()
+    }
+
+    const _LINES: [&str; 5] = ["id,name", "1,ab", "2,cd", "#3,ef", "4,abc"];
+
+    #[test]
+    fn test_filter_by_line_numbers() {
+        let filter = RecordFilter {
+            skip_lines: 1,
+            stop_after: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(vec![2, 3, 4], kept_lines(&filter, &_LINES));
+        assert!(filter.is_past_end(&RecordContext {
+            line_number: 5,
+            ..Default::default()
+        }));
+    }
+
+    #[test]
+    fn test_filter_by_literal() {
+        let filter = RecordFilter {
+            pattern: Some(Pattern::Literal("ab".to_string())),
+            ..Default::default()
+        };
+        assert_eq!(vec![2, 5], kept_lines(&filter, &_LINES));
+    }
+
+    #[test]
+    fn test_filter_by_regex_inverted() {
+        let filter = RecordFilter {
+            skip_lines: 1,
+            pattern: Some(Pattern::Regex(Regex::new("^#").unwrap())),
+            invert_match: true,
+            ..Default::default()
+        };
+        assert_eq!(vec![2, 3, 5], kept_lines(&filter, &_LINES));
+    }
+
+    #[test]
+    fn test_filter_default_keeps_all() {
+        assert_eq!(
+            vec![1, 2, 3, 4, 5],
+            kept_lines(&RecordFilter::default(), &_LINES)
+        );
+    }
+}
@@ -0,0 +1,270 @@
+//! Follow mode: keep reading files as they grow, like `tail -f`.
+//! Truncated files are read again from the start, rotated files
+//! (detected by an inode change on Unix) are reopened once read to their end.
+//! Either way, line numbers start over.
+//!
+
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Seek, SeekFrom};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::record::{strip_line_ending, RecordContext};
+
+/// How long to wait before polling followed files again when no data arrived
+pub const FOLLOW_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Identity of a file that changes when the file is rotated
+type FileId = Option<(u64, u64)>;
+
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> FileId {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> FileId {
+    None
+}
+
+/// Reader of records from a growing file. Only complete lines are returned as records
+pub struct Follower<'a> {
+    file: &'a str,
+    reader: BufReader<File>,
+    file_id: FileId,
+    // Bytes read from the current file, including the pending bytes
+    position: u64,
+    // Byte offset of the pending bytes, kept in the old file across rotations
+    pending_offset: u64,
+    // Bytes of the incomplete last line
+    pending: Vec<u8>,
+    // Whether the file was truncated or rotated, so that line numbers start over
+    restarted: bool,
+    line: String,
+    record: RecordContext<'a>,
+}
+
+impl<'a> Follower<'a> {
+    /// Open the file for following from its start
+    pub fn open(file: &'a str) -> io::Result<Follower<'a>> {
+        let handle = File::open(file)?;
+        let file_id = file_id(&handle.metadata()?);
+
+        Ok(Follower {
+            file,
+            reader: BufReader::new(handle),
+            file_id,
+            position: 0,
+            pending_offset: 0,
+            pending: vec![],
+            restarted: false,
+            line: String::new(),
+            record: RecordContext {
+                file,
+                ..Default::default()
+            },
+        })
+    }
+
+    /// Name of the followed file
+    pub fn file(&self) -> &'a str {
+        self.file
+    }
+
+    /// Line number of the last record read. The first record after a truncation or
+    /// rotation has the line number 1 again
+    pub fn line_number(&self) -> u64 {
+        self.record.line_number
+    }
+
+    /// Read the next complete record. Returns `None` when no more data is available for now
+    pub fn next_record(&mut self) -> io::Result<Option<(&str, &RecordContext<'a>)>> {
+        loop {
+            if self.pending.is_empty() {
+                self.pending_offset = self.position;
+
+                // Records of the new file are numbered from its start, once the old file is done
+                if self.restarted {
+                    self.restarted = false;
+                    self.record.line_number = 0;
+                    self.record.byte_offset = 0;
+                    self.record.byte_len = 0;
+                }
+            }
+
+            let byte_len = self.reader.read_until(b'\n', &mut self.pending)?;
+            self.position += byte_len as u64;
+
+            if byte_len > 0 {
+                if self.pending.ends_with(b"\n") {
+                    return self.take_record().map(Some);
+                }
+                continue;
+            }
+
+            if !self.reopen_if_changed()? {
+                return Ok(None);
+            }
+
+            // The last line of a rotated file may lack its line terminator
+            if !self.pending.is_empty() {
+                return self.take_record().map(Some);
+            }
+        }
+    }
+
+    /// Reopen the file after rotation or rewind it after truncation.
+    /// Returns whether there may be new data to read
+    fn reopen_if_changed(&mut self) -> io::Result<bool> {
+        let metadata = match fs::metadata(Path::new(self.file)) {
+            Ok(metadata) => metadata,
+            // The file is being rotated, wait for it to reappear
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(false),
+            Err(err) => return Err(err),
+        };
+
+        if file_id(&metadata) != self.file_id {
+            // Late writes to the rotated file are read before switching to the new file
+            if !self.reader.fill_buf()?.is_empty() {
+                return Ok(true);
+            }
+
+            let handle = File::open(self.file)?;
+            self.file_id = file_id(&handle.metadata()?);
+            self.reader = BufReader::new(handle);
+            self.position = 0;
+            self.restarted = true;
+            return Ok(true);
+        }
+
+        if metadata.len() < self.position {
+            self.reader.seek(SeekFrom::Start(0))?;
+            self.position = 0;
+            self.pending.clear();
+            self.restarted = true;
+            return Ok(true);
+        }
+
+        Ok(false)
+    }
+
+    fn take_record(&mut self) -> io::Result<(&str, &RecordContext<'a>)> {
+        let pending = std::mem::take(&mut self.pending);
+        self.line = String::from_utf8(pending)
+            .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        self.record.line_number += 1;
+        self.record.byte_offset = self.pending_offset;
+        self.record.byte_len = self.line.len();
+        strip_line_ending(&mut self.line);
+
+        Ok((&self.line, &self.record))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+
+    fn append(path: &Path, text: &str) {
+        OpenOptions::new()
+            .append(true)
+            .open(path)
+            .unwrap()
+            .write_all(text.as_bytes())
+            .unwrap();
+    }
+
+    fn drain(follower: &mut Follower) -> Vec<(String, u64, u64)> {
+        let mut records = vec![];
+        while let Some((line, record)) = follower.next_record().unwrap() {
+            records.push((line.to_string(), record.line_number, record.byte_offset));
+        }
+        records
+    }
+
+    fn owned(records: &[(&str, u64, u64)]) -> Vec<(String, u64, u64)> {
+        records
+            .iter()
+            .map(|(line, line_number, byte_offset)| (line.to_string(), *line_number, *byte_offset))
+            .collect()
+    }
+
+    #[test]
+    fn test_follow_growing_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "a b c\n").unwrap();
+
+        let mut follower = Follower::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(owned(&[("a b c", 1, 0)]), drain(&mut follower));
+        assert_eq!(owned(&[]), drain(&mut follower));
+
+        // Incomplete lines are held back until their terminator arrives
+        append(&path, "d e");
+        assert_eq!(owned(&[]), drain(&mut follower));
+        append(&path, " f\r\ng\n");
+        assert_eq!(owned(&[("d e f", 2, 6), ("g", 3, 13)]), drain(&mut follower));
+    }
+
+    #[test]
+    fn test_follow_truncated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "first line\n").unwrap();
+
+        let mut follower = Follower::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(owned(&[("first line", 1, 0)]), drain(&mut follower));
+
+        fs::write(&path, "new\n").unwrap();
+        assert_eq!(owned(&[("new", 1, 0)]), drain(&mut follower));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_rotated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        fs::write(&path, "old 1\n").unwrap();
+
+        let mut follower = Follower::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(owned(&[("old 1", 1, 0)]), drain(&mut follower));
+
+        append(&path, "old 2");
+        fs::rename(&path, dir.path().join("app.log.1")).unwrap();
+        assert_eq!(owned(&[]), drain(&mut follower));
+
+        fs::write(&path, "new 1\n").unwrap();
+        assert_eq!(
+            owned(&[("old 2", 2, 6), ("new 1", 1, 0)]),
+            drain(&mut follower)
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_follow_late_writes_to_rotated_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("app.log");
+        let rotated = dir.path().join("app.log.1");
+        fs::write(&path, "old 1\n").unwrap();
+
+        let mut follower = Follower::open(path.to_str().unwrap()).unwrap();
+        assert_eq!(owned(&[("old 1", 1, 0)]), drain(&mut follower));
+        assert_eq!(1, follower.line_number());
+
+        // The writer still holds the rotated file when the new file appears
+        fs::rename(&path, &rotated).unwrap();
+        fs::write(&path, "new 1\n").unwrap();
+        append(&rotated, "old 2\n");
+        assert_eq!(
+            owned(&[("old 2", 2, 6), ("new 1", 1, 0)]),
+            drain(&mut follower)
+        );
+        assert_eq!(1, follower.line_number());
+    }
+}
@@ -4,27 +4,197 @@
 
 use std::fs::File;
 use std::io::prelude::*;
-use std::io::{BufReader, BufWriter};
-use std::str;
+use std::io::{BufReader, BufWriter, IsTerminal};
+use std::path::Path;
+use std::{error, fmt, io, str};
+
+#[cfg(feature = "async")]
+mod async_rtool;
+pub mod decompress;
+pub mod encoding;
+pub mod filter;
+pub mod follow;
+pub mod multi;
+mod options;
+pub mod output;
+pub mod peek;
+pub mod record;
+pub mod stateful;
+
+#[cfg(feature = "async")]
+pub use async_rtool::AsyncRtoolT;
+pub use options::RtoolOptions;
+pub use filter::RecordFilter;
+pub use follow::Follower;
+pub use multi::{LockstepReader, MultiLineProcessorT, MultiRtoolT};
+pub use output::OutputTarget;
+pub use peek::{peek_lines, PeekedInputs};
+pub use record::{decode_line, RecordContext, RecordError, RecordReader};
+pub use stateful::{StatefulLineProcessor, StatefulProcessorT};
+
+use encoding::EncodeWriter;
+
+/// File name standing for STDIN in the list of input files
+pub const STDIN_FILE_NAME: &str = "-";
+
+/// Name of the input file as shown in prefixes and headers
+pub fn display_name(file: &str) -> &str {
+    if file == STDIN_FILE_NAME {
+        "(standard input)"
+    } else {
+        file
+    }
+}
+
+/// Files that could not be opened while the other files were processed, like GNU tools
+/// carry on past them. Displayed one file per line, e.g. `a.txt: No such file or directory`
+#[derive(Debug)]
+pub struct UnreadableFiles(pub Vec<(String, io::Error)>);
+
+impl fmt::Display for UnreadableFiles {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, (file, err)) in self.0.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            // Without the ` (os error 2)` suffix, like GNU tools
+            let message = err.to_string();
+            let message = message.split(" (os error ").next().unwrap_or_default();
+            write!(f, "{}: {}", display_name(file), message)?;
+        }
+        Ok(())
+    }
+}
+
+impl error::Error for UnreadableFiles {}
+
+/// Open the input file for reading. The name `-` stands for STDIN.
+/// Inputs start with the lines peeked by `peek_lines`
+pub fn open_input(file: &str, options: &RtoolOptions) -> io::Result<Box<dyn BufRead>> {
+    decode_input(Box::new(BufReader::new(peek::raw_input(file, &options.peeked)?)), options)
+}
+
+/// Decompress and decode the raw input according to the options
+fn decode_input(input: Box<dyn BufRead>, options: &RtoolOptions) -> io::Result<Box<dyn BufRead>> {
+    let input = if options.decompress {
+        decompress::decompress(input)?
+    } else {
+        input
+    };
+
+    match options.encoding {
+        Some(encoding) => Ok(encoding::decode_input(input, encoding)),
+        None if options.keep_bom => Ok(input),
+        None => encoding::sniff_bom(input),
+    }
+}
 
 pub trait LineProcessorT<C> {
     fn process(&self, line: &str, context: &C) -> Vec<u8>;
+
+    /// Process the line knowing where it comes from. Delegates to `process` by default
+    fn process_record(&self, line: &str, _record: &RecordContext, context: &C) -> Vec<u8> {
+        self.process(line, context)
+    }
+
+    /// Process the line or reject it with an error. Never fails by default
+    fn try_process_record(&self, line: &str, record: &RecordContext, context: &C) -> Result<Vec<u8>, RecordError> {
+        Ok(self.process_record(line, record, context))
+    }
+
+    /// Process the whole input at once instead of record by record, e.g. to scan it in chunks.
+    /// Only called when the options filter no record. `None` by default: the records are processed
+    fn try_process_whole_input(&self, _input: &mut dyn BufRead, _context: &C) -> Option<io::Result<Vec<u8>>> {
+        None
+    }
+
+    /// Process the record from its raw bytes, including the line terminator, e.g. to count
+    /// the bytes of lines that are not valid UTF-8. By default, the line is decoded as UTF-8
+    /// and processed by `try_process_record`. Followed files and the async driver always decode
+    fn try_process_raw_record(&self, raw: &[u8], record: &RecordContext, context: &C) -> io::Result<Vec<u8>> {
+        Ok(self.try_process_record(decode_line(raw)?, record, context)?)
+    }
+
+    /// Output before the records of the input file. Nothing by default
+    fn begin_file(&self, _file: &str, _context: &C) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Output after the records of the input file. Nothing by default
+    fn end_file(&self, _file: &str, _context: &C) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Output after all input files. Nothing by default
+    fn finish(&self, _context: &C) -> Vec<u8> {
+        vec![]
+    }
 }
 
 pub trait RtoolT<C, P: LineProcessorT<C>> {
-    /// Generic line processor that delegates to concrete line processors
-    fn process_lines<R: Read, W: Write>(
+    /// Generic line processor that delegates to concrete line processors.
+    /// The input is one of many, so the line processor does not finish
+    fn process_lines<R: BufRead, W: Write>(
         &self,
         line_processor: &P,
-        input: BufReader<R>,
+        input: R,
         output: &mut BufWriter<W>,
         context: &C,
     ) {
-        for line in input.lines() {
-            let out_bytes = line_processor.process(&line.unwrap(), context);
+        self.process_input(line_processor, "", input, output, context, &RtoolOptions::default())
+            .unwrap();
+    }
+
+    /// Process one input according to the options: Send its records to the line processor.
+    /// Records are prefixed with the file name, line number and byte offset on demand.
+    /// Stops at the first record rejected by the line processor
+    fn process_input<R: BufRead, W: Write>(
+        &self,
+        line_processor: &P,
+        file: &str,
+        mut input: R,
+        output: &mut BufWriter<W>,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        if options.file_headers {
+            output.write_all(format!("==> {} <==\n", display_name(file)).as_bytes())?;
+        }
+        output.write_all(&line_processor.begin_file(file, context))?;
+
+        if options.filter.keeps_all() {
+            if let Some(out_bytes) = line_processor.try_process_whole_input(&mut input, context) {
+                output.write_all(&out_bytes?)?;
+                return output.write_all(&line_processor.end_file(file, context));
+            }
+        }
+
+        let annotated = options.with_filename || options.line_numbers || options.byte_offsets;
+        let mut reader = RecordReader::new(file, input);
+
+        while let Some((raw, record)) = reader.next_raw_record()? {
+            if options.filter.is_past_end(record) {
+                break;
+            }
 
-            output.write(&out_bytes).unwrap();
+            if !options.filter.accepts_raw(raw, record)? {
+                continue;
+            }
+
+            let out_bytes = line_processor.try_process_raw_record(raw, record, context)?;
+
+            // Records consumed without output, e.g. headers, are not annotated either
+            if annotated && !out_bytes.is_empty() {
+                output.write_all(record::record_prefix(record, options).as_bytes())?;
+            }
+            output.write_all(&out_bytes)?;
+
+            if options.line_buffered {
+                output.flush()?;
+            }
         }
+
+        output.write_all(&line_processor.end_file(file, context))
     }
 
     /// Process readable object: Send input to the line processor
@@ -35,10 +205,11 @@ pub trait RtoolT<C, P: LineProcessorT<C>> {
         output: &mut BufWriter<W>,
         context: &C,
     ) {
-        self.process_lines(line_processor, input, output, &context);
+        self.process_lines(line_processor, input, output, context);
     }
 
-    /// Process files: Send them to the line processor
+    /// Process files: Send them to the line processor.
+    /// The file `-` stands for STDIN and may appear many times among other files
     fn process_files<W: std::io::Write>(
         &self,
         line_processor: &P,
@@ -46,40 +217,783 @@ pub trait RtoolT<C, P: LineProcessorT<C>> {
         writable: W,
         context: &C,
     ) {
-        // TODO: What can we do about encodings? ASCII vs UTF-8 vs X
-        let mut output = BufWriter::new(writable);
+        self.process_files_with_options(
+            line_processor,
+            files,
+            writable,
+            context,
+            &RtoolOptions::default(),
+        )
+        .unwrap();
+    }
+
+    /// Process files according to the options: Send them to the line processor.
+    /// Unreadable files are skipped and returned as `UnreadableFiles` once the other files
+    /// are processed, other errors stop the processing
+    fn process_files_with_options<W: std::io::Write>(
+        &self,
+        line_processor: &P,
+        files: &Vec<&str>,
+        writable: W,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        let mut output = BufWriter::new(EncodeWriter::new(writable, options.output_encoding));
+        let mut unreadable_files = vec![];
+
+        for (index, file) in files.iter().enumerate() {
+            // Headers of consecutive files are separated by a blank line
+            if options.file_headers && index > 0 {
+                output.write_all(b"\n")?;
+            }
+
+            match open_input(file, options) {
+                Ok(input) => {
+                    self.process_input(line_processor, file, input, &mut output, context, options)?;
+                }
+                Err(err) => unreadable_files.push((file.to_string(), err)),
+            }
+        }
+
+        output.write_all(&line_processor.finish(context))?;
+        encoding::finish_output(output)?;
+
+        match unreadable_files.first() {
+            Some((_, err)) => Err(io::Error::new(err.kind(), UnreadableFiles(unreadable_files))),
+            None => Ok(()),
+        }
+    }
+
+    /// Send the records available so far from the followed file to the line processor.
+    /// The file begins with its first record and begins again after a truncation or rotation,
+    /// ending what was read before. Returns whether any record was read
+    fn process_follower<W: Write>(
+        &self,
+        line_processor: &P,
+        follower: &mut Follower,
+        output: &mut BufWriter<W>,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<bool> {
+        let annotated = options.with_filename || options.line_numbers || options.byte_offsets;
+        let mut any_record = false;
+        let file = follower.file();
+
+        loop {
+            let previous_line_number = follower.line_number();
+            let (line, record) = match follower.next_record()? {
+                Some(next) => next,
+                None => break,
+            };
+            any_record = true;
+
+            if record.line_number == 1 {
+                if previous_line_number > 0 {
+                    output.write_all(&line_processor.end_file(file, context))?;
+                }
+                output.write_all(&line_processor.begin_file(file, context))?;
+            }
+
+            // Unlike regular inputs, followed files are read on after the last selected line
+            // since they may be truncated and rewritten
+            if !options.filter.accepts(line, record) {
+                continue;
+            }
+
+            let out_bytes = line_processor.try_process_record(line, record, context)?;
+
+            // Records consumed without output, e.g. headers, are not annotated either
+            if annotated && !out_bytes.is_empty() {
+                output.write_all(record::record_prefix(record, options).as_bytes())?;
+            }
+            output.write_all(&out_bytes)?;
+
+            if options.line_buffered {
+                output.flush()?;
+            }
+        }
+
+        Ok(any_record)
+    }
+
+    /// Process the files and keep processing whatever is appended to them, like `tail -f`.
+    /// STDIN is read to its end once. Followed files are read as UTF-8 as they are,
+    /// compressed files are rejected. Returns only on errors, so the line processor never finishes
+    fn follow_files<W: Write>(
+        &self,
+        line_processor: &P,
+        files: &Vec<&str>,
+        writable: W,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        let mut output = BufWriter::new(EncodeWriter::new(writable, options.output_encoding));
+        let mut followers = vec![];
+        let stdin_only = vec![STDIN_FILE_NAME];
+        let files = if files.is_empty() { &stdin_only } else { files };
 
         for file in files {
-            match File::open(file) {
-                Ok(file) => {
-                    let input = BufReader::new(file);
-                    self.process_readable(line_processor, input, &mut output, context);
+            if *file == STDIN_FILE_NAME {
+                let input = open_input(file, options)?;
+                self.process_input(line_processor, file, input, &mut output, context, options)?;
+            } else {
+                // Decompressing would need the whole file again after each append
+                if options.decompress {
+                    if let Some(compression) = decompress::detect_compression(&decompress::read_header(File::open(file)?)?) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidInput,
+                            format!("{}: {:?} compressed files cannot be followed", file, compression),
+                        ));
+                    }
+                }
+
+                let follower = Follower::open(file)
+                    .map_err(|err| io::Error::new(err.kind(), format!("`{}`: {}", file, err)))?;
+                followers.push(follower);
+            }
+        }
+
+        if followers.is_empty() {
+            return encoding::finish_output(output);
+        }
+
+        // Headers are written whenever the output switches to another file
+        let mut last_file: Option<&str> = None;
+
+        loop {
+            let mut idle = true;
+
+            for follower in followers.iter_mut() {
+                let mut buffer = BufWriter::new(Vec::new());
+                if !self.process_follower(line_processor, follower, &mut buffer, context, options)? {
+                    continue;
+                }
+                idle = false;
+
+                let buffer = buffer.into_inner().map_err(|err| err.into_error())?;
+                if buffer.is_empty() {
+                    continue;
                 }
-                Err(err) => {
-                    eprintln!("Could not read the file `{}`. The error: {:?}", file, err);
+
+                if options.file_headers && last_file != Some(follower.file()) {
+                    if last_file.is_some() {
+                        output.write_all(b"\n")?;
+                    }
+                    output.write_all(format!("==> {} <==\n", display_name(follower.file())).as_bytes())?;
+                    last_file = Some(follower.file());
                 }
+                output.write_all(&buffer)?;
+            }
+
+            output.flush()?;
+            if idle {
+                std::thread::sleep(follow::FOLLOW_POLL_INTERVAL);
             }
         }
     }
 
     /// Read lines from the input files or STDIN and send them to the processor. Results go to defined output
     fn process<W: std::io::Write>(&self, line_processor: &P, files: &Vec<&str>, writable: &mut W, context: &C) {
-        // TODO: What can we do about encodings? ASCII vs UTF-8 vs X
-        // TODO: Add a method to take BufWriter<W> instead of assuming STDOUT
+        self.process_with_options(line_processor, files, writable, context, &RtoolOptions::default())
+            .unwrap();
+    }
+
+    /// Read lines from the input files or STDIN according to the options. Results go to defined output
+    fn process_with_options<W: std::io::Write>(
+        &self,
+        line_processor: &P,
+        files: &Vec<&str>,
+        writable: &mut W,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
         if files.is_empty() {
-            self.process_readable(
-                line_processor,
-                BufReader::new(std::io::stdin()),
-                &mut BufWriter::new(writable),
-                context,
-            );
+            self.process_files_with_options(line_processor, &vec![STDIN_FILE_NAME], writable, context, options)
         } else {
-            self.process_files(line_processor, &files, writable, context);
+            self.process_files_with_options(line_processor, files, writable, context, options)
         }
     }
 
     /// Read lines from the input files or STDIN and write results to STDOUT
     fn process_to_stdout(&self, line_processor: &P, files: &Vec<&str>, context: &C) {
-        self.process(line_processor, files, &mut BufWriter::new(std::io::stdout()), context);
+        self.process_to_stdout_with_options(line_processor, files, context, &RtoolOptions::default())
+            .unwrap();
+    }
+
+    /// Read lines from the input files or STDIN according to the options and write results to STDOUT
+    /// Output to a terminal is line-buffered
+    fn process_to_stdout_with_options(&self, line_processor: &P, files: &Vec<&str>, context: &C, options: &RtoolOptions) -> io::Result<()> {
+        if !options.line_buffered && io::stdout().is_terminal() {
+            let options = RtoolOptions {
+                line_buffered: true,
+                ..options.clone()
+            };
+            self.process_with_options(line_processor, files, &mut BufWriter::new(std::io::stdout()), context, &options)
+        } else {
+            self.process_with_options(line_processor, files, &mut BufWriter::new(std::io::stdout()), context, options)
+        }
+    }
+
+    /// Read lines from the input files or STDIN and write results to the output target of the options.
+    /// Files are replaced atomically, in-place editing requires named input files
+    fn process_to_output(&self, line_processor: &P, files: &Vec<&str>, context: &C, options: &RtoolOptions) -> io::Result<()> {
+        if options.follow {
+            if options.encoding.is_some() {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "followed files cannot be decoded from another encoding",
+                ));
+            }
+
+            return match &options.output {
+                OutputTarget::Stdout => self.follow_files(line_processor, files, io::stdout(), context, options),
+                // The results never end, so the file is written directly rather than replaced
+                OutputTarget::File(path) => self.follow_files(line_processor, files, File::create(path)?, context, options),
+                _ => Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "followed files can only be written to STDOUT or an output file",
+                )),
+            };
+        }
+
+        match &options.output {
+            OutputTarget::Stdout => self.process_to_stdout_with_options(line_processor, files, context, options),
+            OutputTarget::File(path) => output::replace_atomically(path, None, |file| {
+                self.process_with_options(line_processor, files, file, context, options)
+            }),
+            OutputTarget::InPlace { backup_suffix } => {
+                if files.is_empty() || files.contains(&STDIN_FILE_NAME) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "STDIN cannot be edited in place",
+                    ));
+                }
+
+                // Decompressed results would silently replace compressed files
+                if options.decompress {
+                    for file in files {
+                        if let Some(compression) = decompress::detect_compression(&decompress::read_header(File::open(file)?)?) {
+                            return Err(io::Error::new(
+                                io::ErrorKind::InvalidInput,
+                                format!("{}: {:?} compressed files cannot be edited in place", file, compression),
+                            ));
+                        }
+                    }
+                }
+
+                for file in files {
+                    self.process_file_to_path(line_processor, file, Path::new(file), backup_suffix.as_deref(), context, options)?;
+                }
+
+                Ok(())
+            }
+            OutputTarget::PerFile { template } => {
+                if files.is_empty() || files.contains(&STDIN_FILE_NAME) {
+                    return Err(io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        "STDIN cannot be written to a per-file output",
+                    ));
+                }
+
+                let paths = output::expand_output_templates(template, files)?;
+                for (file, path) in files.iter().zip(paths) {
+                    self.process_file_to_path(line_processor, file, &path, None, context, options)?;
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    /// Process one input file and atomically replace the path by the results.
+    /// The line processor finishes each output
+    fn process_file_to_path(
+        &self,
+        line_processor: &P,
+        file: &str,
+        path: &Path,
+        backup_suffix: Option<&str>,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        // Open the input first so that unreadable files leave the output untouched
+        let input = open_input(file, options)
+            .map_err(|err| io::Error::new(err.kind(), format!("`{}`: {}", file, err)))?;
+
+        output::replace_atomically(path, backup_suffix, |writable| {
+            let mut output = BufWriter::new(EncodeWriter::new(writable, options.output_encoding));
+            self.process_input(line_processor, file, input, &mut output, context, options)?;
+            output.write_all(&line_processor.finish(context))?;
+            encoding::finish_output(output)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    struct UpperLineProcessor {}
+
+    impl LineProcessorT<()> for UpperLineProcessor {
+        fn process(&self, line: &str, _context: &()) -> Vec<u8> {
+            format!("{}\n", line.to_uppercase()).into_bytes()
+        }
+    }
+
+    struct UpperTool {}
+
+    /// Line processor marking the beginning and the end of each file and of all files
+    struct MarkingLineProcessor {}
+
+    impl LineProcessorT<()> for MarkingLineProcessor {
+        fn process(&self, line: &str, _context: &()) -> Vec<u8> {
+            format!("{}\n", line).into_bytes()
+        }
+
+        fn begin_file(&self, file: &str, _context: &()) -> Vec<u8> {
+            format!("<{}>\n", display_name(file)).into_bytes()
+        }
+
+        fn end_file(&self, _file: &str, _context: &()) -> Vec<u8> {
+            b"</>\n".to_vec()
+        }
+
+        fn finish(&self, _context: &()) -> Vec<u8> {
+            b"end\n".to_vec()
+        }
+    }
+
+    struct MarkingTool {}
+
+    impl RtoolT<(), MarkingLineProcessor> for MarkingTool {}
+
+    impl RtoolT<(), UpperLineProcessor> for UpperTool {}
+
+    /// Write the named files into a temp dir and return their paths
+    fn write_files(dir: &tempfile::TempDir, files: &[(&str, &str)]) -> Vec<String> {
+        files
+            .iter()
+            .map(|(name, content)| {
+                let path = dir.path().join(name);
+                fs::write(&path, content).unwrap();
+                path.to_str().unwrap().to_string()
+            })
+            .collect()
+    }
+
+    fn process_to_vec(files: &[String], options: &RtoolOptions) -> String {
+        let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+        let mut out = Vec::new();
+        UpperTool {}
+            .process_files_with_options(&UpperLineProcessor {}, &files, &mut out, &(), options)
+            .unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_open_input_stdin() {
+        // Sniffing BOMs would wait for the first bytes of STDIN
+        let options = RtoolOptions {
+            keep_bom: true,
+            ..Default::default()
+        };
+        assert!(open_input(STDIN_FILE_NAME, &options).is_ok());
+    }
+
+    #[test]
+    fn test_open_input_missing_file() {
+        let err = open_input("/nonexistent/rtools-traits/input.txt", &RtoolOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+    }
+
+    #[test]
+    fn test_open_input_file() {
+        let mut input = String::new();
+        open_input("Cargo.toml", &RtoolOptions::default())
+            .unwrap()
+            .read_to_string(&mut input)
+            .unwrap();
+        assert!(input.contains("rtools-traits"));
+    }
+
+    #[test]
+    fn test_process_input_from_stdin() {
+        let mut out = BufWriter::new(Vec::new());
+        MarkingTool {}
+            .process_input(
+                &MarkingLineProcessor {},
+                STDIN_FILE_NAME,
+                io::Cursor::new("s1\ns2"),
+                &mut out,
+                &(),
+                &RtoolOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(
+            "<(standard input)>\ns1\ns2\n</>\n",
+            String::from_utf8(out.into_inner().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_process_files_with_filename() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\na2\n"), ("b.txt", "b1\n")]);
+        let options = RtoolOptions {
+            with_filename: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("{0}:A1\n{0}:A2\n{1}:B1\n", files[0], files[1]),
+            process_to_vec(&files, &options)
+        );
+    }
+
+    struct NumberingLineProcessor {}
+
+    impl LineProcessorT<()> for NumberingLineProcessor {
+        fn process(&self, line: &str, _context: &()) -> Vec<u8> {
+            format!("{}\n", line).into_bytes()
+        }
+
+        fn process_record(&self, line: &str, record: &RecordContext, _context: &()) -> Vec<u8> {
+            format!("{}#{}@{}\n", line, record.line_number, record.byte_offset).into_bytes()
+        }
+    }
+
+    struct NumberingTool {}
+
+    impl RtoolT<(), NumberingLineProcessor> for NumberingTool {}
+
+    #[test]
+    fn test_process_files_with_line_numbers_and_byte_offsets() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\r\na2\n"), ("b.txt", "b1\n")]);
+        let options = RtoolOptions {
+            line_numbers: true,
+            byte_offsets: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            "1:0:A1\n2:4:A2\n1:0:B1\n",
+            process_to_vec(&files, &options)
+        );
+    }
+
+    #[test]
+    fn test_process_files_with_filter() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "h\na1\nb2\na3\na4\n"), ("b.txt", "h\na5\n")]);
+        let options = RtoolOptions {
+            line_numbers: true,
+            filter: RecordFilter {
+                skip_lines: 1,
+                stop_after: Some(4),
+                pattern: Some(filter::Pattern::Literal("a".to_string())),
+                invert_match: false,
+            },
+            ..Default::default()
+        };
+
+        assert_eq!("2:A1\n4:A3\n2:A5\n", process_to_vec(&files, &options));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_process_files_with_unreadable_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\n")]);
+        let files = vec!["missing-1", files[0].as_str(), "missing-2"];
+
+        // The readable files are processed nonetheless
+        let mut out = Vec::new();
+        let err = UpperTool {}
+            .process_files_with_options(&UpperLineProcessor {}, &files, &mut out, &(), &RtoolOptions::default())
+            .err()
+            .unwrap();
+        assert_eq!("A1\n", String::from_utf8(out).unwrap());
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        assert_eq!(
+            "missing-1: No such file or directory\nmissing-2: No such file or directory",
+            err.to_string()
+        );
+        assert!(err.get_ref().unwrap().downcast_ref::<UnreadableFiles>().is_some());
+    }
+
+    /// Line processor counting the bytes of whole inputs
+    struct WholeInputLineProcessor {}
+
+    impl LineProcessorT<()> for WholeInputLineProcessor {
+        fn process(&self, line: &str, _context: &()) -> Vec<u8> {
+            format!("{}\n", line).into_bytes()
+        }
+
+        fn try_process_whole_input(&self, input: &mut dyn BufRead, _context: &()) -> Option<io::Result<Vec<u8>>> {
+            let mut content = vec![];
+            Some(input.read_to_end(&mut content).map(|len| format!("{} bytes\n", len).into_bytes()))
+        }
+    }
+
+    struct WholeTool {}
+
+    impl RtoolT<(), WholeInputLineProcessor> for WholeTool {}
+
+    #[test]
+    fn test_process_whole_input() {
+        let process = |options: &RtoolOptions| {
+            let mut output = BufWriter::new(Vec::new());
+            WholeTool {}
+                .process_input(&WholeInputLineProcessor {}, "", io::Cursor::new("a\nb\n"), &mut output, &(), options)
+                .unwrap();
+            String::from_utf8(output.into_inner().unwrap()).unwrap()
+        };
+
+        assert_eq!("4 bytes\n", process(&RtoolOptions::default()));
+        // Filtered records are processed one by one
+        let options = RtoolOptions {
+            filter: RecordFilter {
+                skip_lines: 1,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!("b\n", process(&options));
+    }
+
+    #[test]
+    fn test_process_lines_exposes_records() {
+        let mut out = Vec::new();
+        NumberingTool {}.process_lines(
+            &NumberingLineProcessor {},
+            std::io::Cursor::new("x\n🐣\ny"),
+            &mut BufWriter::new(&mut out),
+            &(),
+        );
+        assert_eq!("x#1@0\n🐣#2@2\ny#3@7\n".as_bytes().to_vec(), out);
+    }
+
+    /// Writer recording the output at each flush
+    #[derive(Default)]
+    struct FlushRecorder {
+        out: Vec<u8>,
+        flushed: Vec<String>,
+    }
+
+    impl Write for FlushRecorder {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.out.write(buf)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            self.flushed.push(String::from_utf8(self.out.clone()).unwrap());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_process_input_line_buffered() {
+        let mut recorder = FlushRecorder::default();
+        let options = RtoolOptions {
+            line_buffered: true,
+            ..Default::default()
+        };
+        UpperTool {}.process_input(
+            &UpperLineProcessor {},
+            "",
+            std::io::Cursor::new("a\nb\n"),
+            &mut BufWriter::new(&mut recorder),
+            &(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(vec!["A\n", "A\nB\n"], recorder.flushed);
+    }
+
+    struct RejectingLineProcessor {}
+
+    impl LineProcessorT<()> for RejectingLineProcessor {
+        fn process(&self, line: &str, _context: &()) -> Vec<u8> {
+            format!("{}\n", line).into_bytes()
+        }
+
+        fn try_process_record(&self, line: &str, record: &RecordContext, context: &()) -> Result<Vec<u8>, RecordError> {
+            if line.is_empty() {
+                Err(RecordError::new(record, "empty line"))
+            } else {
+                Ok(self.process(line, context))
+            }
+        }
+    }
+
+    struct RejectingTool {}
+
+    impl RtoolT<(), RejectingLineProcessor> for RejectingTool {}
+
+    #[test]
+    fn test_process_files_stops_at_rejected_record() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\n\na3\n"), ("b.txt", "b1\n")]);
+        let files: Vec<&str> = files.iter().map(String::as_str).collect();
+        let mut out = vec![];
+
+        let err = RejectingTool {}
+            .process_files_with_options(&RejectingLineProcessor {}, &files, &mut out, &(), &RtoolOptions::default())
+            .unwrap_err();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!(format!("{}:2: empty line", files[0]), err.to_string());
+        assert_eq!("a1\n", String::from_utf8(out).unwrap());
+    }
+
+    #[test]
+    fn test_process_files_with_file_headers() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\n"), ("b.txt", "b1\n")]);
+        let options = RtoolOptions {
+            file_headers: true,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            format!("==> {} <==\nA1\n\n==> {} <==\nB1\n", files[0], files[1]),
+            process_to_vec(&files, &options)
+        );
+    }
+
+    #[test]
+    fn test_process_follower() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("app.log", "a1\n#a2\n")]);
+        let options = RtoolOptions {
+            line_numbers: true,
+            filter: RecordFilter {
+                pattern: Some(filter::Pattern::Literal("#".to_string())),
+                invert_match: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut follower = Follower::open(&files[0]).unwrap();
+        let mut output = BufWriter::new(Vec::new());
+        let tool = UpperTool {};
+
+        assert!(tool.process_follower(&UpperLineProcessor {}, &mut follower, &mut output, &(), &options).unwrap());
+        assert!(!tool.process_follower(&UpperLineProcessor {}, &mut follower, &mut output, &(), &options).unwrap());
+
+        fs::OpenOptions::new().append(true).open(&files[0]).unwrap().write_all(b"a3\n").unwrap();
+        assert!(tool.process_follower(&UpperLineProcessor {}, &mut follower, &mut output, &(), &options).unwrap());
+        assert_eq!("1:A1\n3:A3\n", String::from_utf8(output.into_inner().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_process_follower_begins_and_ends_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("app.log", "a1\na2\n")]);
+        let mut follower = Follower::open(&files[0]).unwrap();
+        let mut output = BufWriter::new(Vec::new());
+        let tool = MarkingTool {};
+        let options = RtoolOptions::default();
+
+        assert!(tool.process_follower(&MarkingLineProcessor {}, &mut follower, &mut output, &(), &options).unwrap());
+        // The truncated file begins again
+        fs::write(&files[0], "b\n").unwrap();
+        assert!(tool.process_follower(&MarkingLineProcessor {}, &mut follower, &mut output, &(), &options).unwrap());
+        assert_eq!(
+            format!("<{0}>\na1\na2\n</>\n<{0}>\nb\n", files[0]),
+            String::from_utf8(output.into_inner().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_follow_rejects_encoding_and_compressed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("app.log.gz", "")]);
+        fs::write(&files[0], b"\x1f\x8b\x08").unwrap();
+        let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+
+        let options = RtoolOptions {
+            follow: true,
+            decompress: true,
+            ..Default::default()
+        };
+        let err = UpperTool {}
+            .process_to_output(&UpperLineProcessor {}, &files, &(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+
+        let options = RtoolOptions {
+            follow: true,
+            encoding: encoding::encoding_for_label("utf-16le"),
+            ..Default::default()
+        };
+        let err = UpperTool {}
+            .process_to_output(&UpperLineProcessor {}, &files, &(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+    }
+
+    #[test]
+    fn test_process_to_per_file_outputs() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\n"), ("b.txt", "b1\nb2\n")]);
+        let files: Vec<&str> = files.iter().map(|file| file.as_str()).collect();
+        let options = RtoolOptions {
+            output: OutputTarget::PerFile {
+                template: "{stem}.upper.{ext}".to_string(),
+            },
+            ..Default::default()
+        };
+
+        UpperTool {}
+            .process_to_output(&UpperLineProcessor {}, &files, &(), &options)
+            .unwrap();
+        assert_eq!("A1\n", fs::read_to_string(dir.path().join("a.upper.txt")).unwrap());
+        assert_eq!("B1\nB2\n", fs::read_to_string(dir.path().join("b.upper.txt")).unwrap());
+        // Inputs are left untouched
+        assert_eq!("a1\n", fs::read_to_string(files[0]).unwrap());
+    }
+
+    #[test]
+    fn test_process_in_place_rejects_compressed_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let files = write_files(&dir, &[("a.txt", "a1\n"), ("b.txt.gz", "")]);
+        fs::write(&files[1], b"\x1f\x8b\x08").unwrap();
+        let files: Vec<&str> = files.iter().map(String::as_str).collect();
+        let options = RtoolOptions {
+            decompress: true,
+            output: OutputTarget::InPlace { backup_suffix: None },
+            ..Default::default()
+        };
+
+        let err = UpperTool {}
+            .process_to_output(&UpperLineProcessor {}, &files, &(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        // No file is edited
+        assert_eq!("a1\n", fs::read_to_string(files[0]).unwrap());
+    }
+
+    #[test]
+    fn test_process_to_per_file_outputs_rejects_stdin() {
+        let options = RtoolOptions {
+            output: OutputTarget::PerFile {
+                template: "{name}.out".to_string(),
+            },
+            ..Default::default()
+        };
+
+        let err = UpperTool {}
+            .process_to_output(&UpperLineProcessor {}, &vec![STDIN_FILE_NAME], &(), &options)
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::InvalidInput, err.kind());
     }
 }
@@ -0,0 +1,175 @@
+//! Lockstep reading of several inputs for tools like `paste` or `join`:
+//! The inputs are advanced together, line by line.
+//!
+
+use std::io::{self, BufRead, BufWriter, Write};
+
+use crate::encoding::{self, EncodeWriter};
+use crate::{open_input, RecordReader, RtoolOptions, STDIN_FILE_NAME};
+
+/// Processor of the lines read together from several inputs
+pub trait MultiLineProcessorT<C> {
+    /// Process one line per input, in the order of the inputs. Inputs past their end yield `None`
+    fn process_lines(&self, lines: &[Option<&str>], context: &C) -> Vec<u8>;
+}
+
+/// Reader advancing several inputs together.
+/// STDIN has only one reader: its lines are taken in turns when it is given many times
+pub struct LockstepReader<'a> {
+    readers: Vec<RecordReader<'a, Box<dyn BufRead>>>,
+    // Index of the reader of each input
+    reader_indices: Vec<usize>,
+    lines: Vec<Option<String>>,
+}
+
+impl<'a> LockstepReader<'a> {
+    /// Reader of the inputs already opened, each named after its file
+    pub fn new(inputs: Vec<(&'a str, Box<dyn BufRead>)>) -> LockstepReader<'a> {
+        let readers: Vec<_> = inputs
+            .into_iter()
+            .map(|(file, input)| RecordReader::new(file, input))
+            .collect();
+
+        LockstepReader {
+            lines: vec![None; readers.len()],
+            reader_indices: (0..readers.len()).collect(),
+            readers,
+        }
+    }
+
+    /// Open all files first, so that unreadable files fail before any line is read.
+    /// The file `-` stands for STDIN
+    pub fn open(files: &[&'a str], options: &RtoolOptions) -> io::Result<LockstepReader<'a>> {
+        let mut readers = vec![];
+        let mut reader_indices = vec![];
+        let mut stdin_index = None;
+
+        for file in files {
+            let reader_index = match stdin_index {
+                Some(stdin_index) if *file == STDIN_FILE_NAME => stdin_index,
+                _ => {
+                    let input = open_input(file, options)
+                        .map_err(|err| io::Error::new(err.kind(), format!("`{}`: {}", file, err)))?;
+                    readers.push(RecordReader::new(file, input));
+                    if *file == STDIN_FILE_NAME {
+                        stdin_index = Some(readers.len() - 1);
+                    }
+                    readers.len() - 1
+                }
+            };
+            reader_indices.push(reader_index);
+        }
+
+        Ok(LockstepReader {
+            readers,
+            lines: vec![None; reader_indices.len()],
+            reader_indices,
+        })
+    }
+
+    /// Read the next line of each input. Returns `None` once all inputs ended
+    pub fn next_lines(&mut self) -> io::Result<Option<Vec<Option<&str>>>> {
+        for (line, reader_index) in self.lines.iter_mut().zip(self.reader_indices.iter()) {
+            match self.readers[*reader_index].next_record()? {
+                Some((next_line, _)) => {
+                    let line = line.get_or_insert_with(String::new);
+                    line.clear();
+                    line.push_str(next_line);
+                }
+                None => *line = None,
+            }
+        }
+
+        if self.lines.iter().all(Option::is_none) {
+            return Ok(None);
+        }
+
+        Ok(Some(self.lines.iter().map(Option::as_deref).collect()))
+    }
+}
+
+pub trait MultiRtoolT<C, P: MultiLineProcessorT<C>> {
+    /// Read the files in lockstep and send their lines to the line processor until all files end
+    fn process_files_in_lockstep<W: Write>(
+        &self,
+        line_processor: &P,
+        files: &Vec<&str>,
+        writable: W,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        let reader = LockstepReader::open(files, options)?;
+        self.process_lockstep(line_processor, reader, writable, context, options)
+    }
+
+    /// Send the lines of the reader to the line processor until all its inputs end
+    fn process_lockstep<W: Write>(
+        &self,
+        line_processor: &P,
+        mut reader: LockstepReader,
+        writable: W,
+        context: &C,
+        options: &RtoolOptions,
+    ) -> io::Result<()> {
+        let mut output = BufWriter::new(EncodeWriter::new(writable, options.output_encoding));
+
+        while let Some(lines) = reader.next_lines()? {
+            output.write_all(&line_processor.process_lines(&lines, context))?;
+
+            if options.line_buffered {
+                output.flush()?;
+            }
+        }
+
+        encoding::finish_output(output)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    struct EofMarkingLineProcessor {}
+
+    impl MultiLineProcessorT<()> for EofMarkingLineProcessor {
+        fn process_lines(&self, lines: &[Option<&str>], _context: &()) -> Vec<u8> {
+            let lines: Vec<&str> = lines.iter().map(|line| line.unwrap_or("<EOF>")).collect();
+            format!("{}\n", lines.join("|")).into_bytes()
+        }
+    }
+
+    struct EofMarkingTool {}
+
+    impl MultiRtoolT<(), EofMarkingLineProcessor> for EofMarkingTool {}
+
+    fn input(content: &'static str) -> Box<dyn BufRead> {
+        Box::new(Cursor::new(content))
+    }
+
+    #[test]
+    fn test_process_lockstep() {
+        let reader = LockstepReader::new(vec![
+            ("a", input("a1\r\na2\na3")),
+            ("b", input("🐣\n")),
+            ("a", input("a1\na2\na3\n")),
+        ]);
+
+        let mut out = vec![];
+        EofMarkingTool {}
+            .process_lockstep(&EofMarkingLineProcessor {}, reader, &mut out, &(), &RtoolOptions::default())
+            .unwrap();
+        assert_eq!(
+            "a1|🐣|a1\na2|<EOF>|a2\na3|<EOF>|a3\n",
+            String::from_utf8(out).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_lockstep_reader_fails_on_unreadable_file() {
+        let err = LockstepReader::open(&["no-such-file"], &RtoolOptions::default())
+            .err()
+            .unwrap();
+        assert!(err.to_string().starts_with("`no-such-file`: "));
+    }
+}
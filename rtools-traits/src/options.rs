@@ -0,0 +1,40 @@
+//! Options shared by all tools built on `RtoolT`.
+//!
+
+use crate::encoding::Encoding;
+use crate::filter::RecordFilter;
+use crate::output::OutputTarget;
+use crate::peek::PeekedInputs;
+
+/// Options controlling how inputs are read and outputs are written
+#[derive(Clone, Debug, Default)]
+pub struct RtoolOptions {
+    /// Decompress inputs detected as gzip/zstd/xz/bzip2 by their magic bytes
+    pub decompress: bool,
+    /// Decode inputs from this encoding into UTF-8. A BOM takes precedence.
+    /// Without encoding, UTF-8 BOMs are stripped and inputs with a UTF-16 BOM are decoded
+    pub encoding: Option<&'static Encoding>,
+    /// Keep BOMs as bytes of the inputs rather than sniffing them, e.g. to count raw bytes
+    pub keep_bom: bool,
+    /// Re-encode outputs from UTF-8 into this encoding
+    pub output_encoding: Option<&'static Encoding>,
+    /// Write results to STDOUT, a file, back into the input files or to per-file outputs
+    pub output: OutputTarget,
+    /// Prefix each record with the name of its input file
+    pub with_filename: bool,
+    /// Write a header with the file name before the records of each input file
+    pub file_headers: bool,
+    /// Prefix each record with its line number within its file
+    pub line_numbers: bool,
+    /// Prefix each record with its byte offset within its (decoded) file
+    pub byte_offsets: bool,
+    /// Select records by line numbers and patterns before processing them
+    pub filter: RecordFilter,
+    /// Keep reading the input files as they grow, like `tail -f`
+    pub follow: bool,
+    /// Flush the output after each record instead of when the buffer is full.
+    /// Enabled automatically when STDOUT is a terminal
+    pub line_buffered: bool,
+    /// Inputs peeked by `peek_lines`, opened again from their start by `open_input`
+    pub peeked: PeekedInputs,
+}
@@ -0,0 +1,339 @@
+//! Output targets: STDOUT, a named file, in-place replacement of inputs or per-file outputs.
+//! Regular files are written atomically via a temp file in the same directory.
+//!
+
+use std::ffi::OsStr;
+use std::fs::{self, File, OpenOptions};
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where tools write their results
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum OutputTarget {
+    /// Write all results to STDOUT
+    #[default]
+    Stdout,
+    /// Write all results to the file
+    File(PathBuf),
+    /// Replace each input file by its results, optionally keeping a backup
+    /// named after the input with the suffix appended
+    InPlace { backup_suffix: Option<String> },
+    /// Write the results of each input file to its own file named by the template,
+    /// see `expand_output_template`
+    PerFile { template: String },
+}
+
+/// Path of the backup file for the given path and suffix
+pub fn backup_path(path: &Path, backup_suffix: &str) -> PathBuf {
+    let mut backup = path.as_os_str().to_owned();
+    backup.push(backup_suffix);
+    PathBuf::from(backup)
+}
+
+/// Name the output of the input file after the template. The placeholders
+/// `{name}`, `{stem}` and `{ext}` stand for the file name, the file name without
+/// extension, and the extension. `.{ext}` is dropped for files without extension.
+/// Relative names are siblings of the input file
+pub fn expand_output_template(template: &str, file: &str) -> PathBuf {
+    let path = Path::new(file);
+    let part = |part: Option<&OsStr>| {
+        part.map(|part| part.to_string_lossy().into_owned())
+            .unwrap_or_default()
+    };
+
+    let ext = part(path.extension());
+    let template = if ext.is_empty() {
+        template.replace(".{ext}", "")
+    } else {
+        template.to_string()
+    };
+
+    let name = template
+        .replace("{name}", &part(path.file_name()))
+        .replace("{stem}", &part(path.file_stem()))
+        .replace("{ext}", &ext);
+
+    match path.parent() {
+        Some(dir) => dir.join(name),
+        None => PathBuf::from(name),
+    }
+}
+
+/// Name the outputs of the input files after the template, see `expand_output_template`.
+/// Outputs overwriting an input or the output of another input are `InvalidInput` errors
+pub fn expand_output_templates(template: &str, files: &[&str]) -> io::Result<Vec<PathBuf>> {
+    let inputs: Vec<PathBuf> = files.iter().map(|file| same_file_key(Path::new(file))).collect();
+    let mut outputs: Vec<PathBuf> = vec![];
+    let mut keys: Vec<PathBuf> = vec![];
+
+    for file in files {
+        let output = expand_output_template(template, file);
+        let key = same_file_key(&output);
+
+        let overwritten = if inputs.contains(&key) {
+            Some("an input file")
+        } else if keys.contains(&key) {
+            Some("the output of another input file")
+        } else {
+            None
+        };
+        if let Some(overwritten) = overwritten {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "the output `{}` of `{}` would overwrite {}",
+                    output.display(),
+                    file,
+                    overwritten
+                ),
+            ));
+        }
+
+        outputs.push(output);
+        keys.push(key);
+    }
+
+    Ok(outputs)
+}
+
+/// Path identifying the file: Links and relative parts are resolved as far as the path exists
+fn same_file_key(path: &Path) -> PathBuf {
+    if let Ok(path) = fs::canonicalize(path) {
+        return path;
+    }
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    match (fs::canonicalize(dir), path.file_name()) {
+        (Ok(dir), Some(name)) => dir.join(name),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Maximum number of symbolic links followed to resolve a path, like Linux does
+const MAX_SYMLINKS: usize = 40;
+
+/// Follow the symbolic links of the path, the last target may not exist yet
+fn resolve_symlinks(path: &Path) -> io::Result<PathBuf> {
+    let mut path = path.to_path_buf();
+
+    for _ in 0..MAX_SYMLINKS {
+        match fs::symlink_metadata(&path) {
+            Ok(metadata) if metadata.file_type().is_symlink() => {
+                // Relative targets are relative to the directory of the link
+                let target = fs::read_link(&path)?;
+                path = match path.parent() {
+                    Some(dir) => dir.join(target),
+                    None => target,
+                };
+            }
+            _ => return Ok(path),
+        }
+    }
+
+    Err(io::Error::other(format!("{}: too many levels of symbolic links", path.display())))
+}
+
+/// Write into a temp file next to the path and atomically rename it over the path,
+/// so that the path is left untouched on error. Permissions of the replaced file are preserved,
+/// new files get the default permissions. With a backup suffix, the replaced file is kept as a backup.
+/// Symbolic links are written through. Devices and FIFOs, e.g. `/dev/null`, are written directly
+pub fn replace_atomically<F>(path: &Path, backup_suffix: Option<&str>, write: F) -> io::Result<()>
+where
+    F: FnOnce(&mut File) -> io::Result<()>,
+{
+    let metadata = match fs::metadata(path) {
+        // Links like `/dev/stdout` may point to pipes, so they are opened as they are
+        Ok(metadata) if !metadata.is_file() => {
+            return write(&mut OpenOptions::new().write(true).open(path)?);
+        }
+        Ok(metadata) => Some(metadata),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => return Err(err),
+    };
+    let path = &resolve_symlinks(path)?;
+
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+
+    // The temp file is removed on error before it gets persisted
+    let mut builder = tempfile::Builder::new();
+    builder.prefix(".rtools-").suffix(".tmp");
+    #[cfg(unix)]
+    {
+        // Temp files are private by default, the umask applies to new files as to `File::create`
+        use std::os::unix::fs::PermissionsExt;
+        builder.permissions(fs::Permissions::from_mode(0o666));
+    }
+    let mut temp = builder.tempfile_in(dir)?;
+    write(temp.as_file_mut())?;
+    temp.as_file().sync_all()?;
+
+    if let Some(metadata) = metadata {
+        fs::set_permissions(temp.path(), metadata.permissions())?;
+
+        if let Some(backup_suffix) = backup_suffix {
+            // Hard links keep the original untouched and in place until the rename
+            let backup = backup_path(path, backup_suffix);
+            if backup.exists() {
+                fs::remove_file(&backup)?;
+            }
+            if fs::hard_link(path, &backup).is_err() {
+                fs::copy(path, &backup)?;
+            }
+        }
+    }
+
+    temp.persist(path).map_err(|err| err.error)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_backup_path() {
+        assert_eq!(
+            PathBuf::from("dir/a.csv.bak"),
+            backup_path(Path::new("dir/a.csv"), ".bak")
+        );
+    }
+
+    #[test]
+    fn test_expand_output_template() {
+        assert_eq!(
+            PathBuf::from("data/a.cut.csv"),
+            expand_output_template("{stem}.cut.{ext}", "data/a.csv")
+        );
+        assert_eq!(
+            PathBuf::from("a.csv.out"),
+            expand_output_template("{name}.out", "a.csv")
+        );
+        assert_eq!(
+            PathBuf::from("/tmp/out/a"),
+            expand_output_template("/tmp/out/{stem}", "data/a.csv")
+        );
+        assert_eq!(
+            PathBuf::from("data/README.cut"),
+            expand_output_template("{stem}.cut.{ext}", "data/README")
+        );
+    }
+
+    #[test]
+    fn test_expand_output_templates_rejects_collisions() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.csv");
+        let b = dir.path().join("b.csv");
+        fs::write(&a, "a\n").unwrap();
+        fs::write(&b, "b\n").unwrap();
+        let files = vec![a.to_str().unwrap(), b.to_str().unwrap()];
+
+        assert_eq!(
+            vec![dir.path().join("a.cut.csv"), dir.path().join("b.cut.csv")],
+            expand_output_templates("{stem}.cut.{ext}", &files).unwrap()
+        );
+        for template in &["{name}", "all.csv", "b.{ext}"] {
+            let err = expand_output_templates(template, &files).err().unwrap();
+            assert_eq!(io::ErrorKind::InvalidInput, err.kind());
+        }
+    }
+
+    #[test]
+    fn test_replace_atomically_new_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+
+        replace_atomically(&path, None, |file| file.write_all(b"new\n")).unwrap();
+        assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+    }
+
+    #[test]
+    fn test_replace_atomically_new_file_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("out.txt");
+
+        let result = replace_atomically(&path, None, |file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::other("failed"))
+        });
+        assert!(result.is_err());
+        // Neither the file nor the temp file are left
+        assert_eq!(0, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn test_replace_atomically_with_backup() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        replace_atomically(&path, Some(".orig"), |file| file.write_all(b"new\n")).unwrap();
+        assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(
+            "old\n",
+            fs::read_to_string(backup_path(&path, ".orig")).unwrap()
+        );
+        // Only the file and its backup are left, no temp files
+        assert_eq!(2, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn test_replace_atomically_keeps_file_on_error() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in.txt");
+        fs::write(&path, "old\n").unwrap();
+
+        let result = replace_atomically(&path, None, |file| {
+            file.write_all(b"partial")?;
+            Err(io::Error::other("failed"))
+        });
+        assert!(result.is_err());
+        assert_eq!("old\n", fs::read_to_string(&path).unwrap());
+        assert_eq!(1, fs::read_dir(dir.path()).unwrap().count());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_atomically_through_symlink() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("in.txt");
+        let link = dir.path().join("link.txt");
+        fs::write(&path, "old\n").unwrap();
+        std::os::unix::fs::symlink("in.txt", &link).unwrap();
+
+        replace_atomically(&link, None, |file| file.write_all(b"new\n")).unwrap();
+        assert!(fs::symlink_metadata(&link).unwrap().file_type().is_symlink());
+        assert_eq!("new\n", fs::read_to_string(&path).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_atomically_writes_devices_directly() {
+        use std::os::unix::fs::FileTypeExt;
+
+        let path = Path::new("/dev/null");
+        replace_atomically(path, None, |file| file.write_all(b"new\n")).unwrap();
+        assert!(fs::metadata(path).unwrap().file_type().is_char_device());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_replace_atomically_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("script.sh");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o751)).unwrap();
+
+        replace_atomically(&path, None, |file| file.write_all(b"new\n")).unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(0o751, mode & 0o777);
+    }
+}
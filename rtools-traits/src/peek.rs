@@ -0,0 +1,192 @@
+//! Peek-ahead of the first lines of inputs, e.g. to sniff their format,
+//! without consuming them: the peeked bytes are read again before the rest of the input.
+//!
+
+use std::cell::RefCell;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Cursor, Read};
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+
+use crate::record::strip_line_ending;
+use crate::{decode_input, RtoolOptions, STDIN_FILE_NAME};
+
+/// Raw input starting with its peeked bytes
+type PeekedInput = Box<dyn Read + Send>;
+
+/// Raw inputs peeked so far by file name, starting with their peeked bytes.
+/// They are kept open, so that pipes and FIFOs are not read from a fresh start.
+/// Clones of the options share their peeked inputs, each input is handed out once
+#[derive(Clone, Default)]
+pub struct PeekedInputs(Arc<Mutex<Vec<(String, PeekedInput)>>>);
+
+impl PeekedInputs {
+    fn take(&self, file: &str) -> Option<PeekedInput> {
+        let mut inputs = self.0.lock().unwrap();
+        let index = inputs.iter().position(|(name, _)| name == file)?;
+        Some(inputs.remove(index).1)
+    }
+
+    fn put(&self, file: &str, input: PeekedInput) {
+        self.0.lock().unwrap().push((file.to_string(), input));
+    }
+}
+
+impl fmt::Debug for PeekedInputs {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list()
+            .entries(self.0.lock().unwrap().iter().map(|(name, _)| name))
+            .finish()
+    }
+}
+
+/// Reader recording the bytes it reads
+struct TeeReader<R> {
+    inner: R,
+    recorded: Rc<RefCell<Vec<u8>>>,
+}
+
+impl<R: Read> Read for TeeReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let len = self.inner.read(buf)?;
+        self.recorded.borrow_mut().extend_from_slice(&buf[..len]);
+        Ok(len)
+    }
+}
+
+/// Reader shared with the peeking decoders, so that it can be taken back once they are done
+struct SharedReader(Rc<RefCell<Box<dyn Read + Send>>>);
+
+impl Read for SharedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.0.borrow_mut().read(buf)
+    }
+}
+
+/// Raw input of the file starting with the bytes peeked so far. The file `-` stands for STDIN
+pub(crate) fn raw_input(file: &str, peeked: &PeekedInputs) -> io::Result<Box<dyn Read + Send>> {
+    if let Some(input) = peeked.take(file) {
+        return Ok(input);
+    }
+
+    if file == STDIN_FILE_NAME {
+        Ok(Box::new(io::stdin()))
+    } else {
+        Ok(Box::new(File::open(file)?))
+    }
+}
+
+/// Read up to `count` first lines of the input file as `open_input` would, without line terminators.
+/// The lines are not consumed: Opening the file again with the options, STDIN included,
+/// starts from its beginning
+pub fn peek_lines(file: &str, count: usize, options: &RtoolOptions) -> io::Result<Vec<String>> {
+    // Decoders may read ahead, so all raw bytes read are put back
+    let inner = Rc::new(RefCell::new(raw_input(file, &options.peeked)?));
+    let recorded = Rc::new(RefCell::new(vec![]));
+    let tee = TeeReader {
+        inner: SharedReader(Rc::clone(&inner)),
+        recorded: Rc::clone(&recorded),
+    };
+
+    let lines = decode_input(Box::new(BufReader::new(tee)), options).and_then(|input| read_lines(input, count));
+
+    // The decoders are dropped by now, the raw input is no longer shared
+    let rest = Rc::try_unwrap(inner).ok().unwrap().into_inner();
+    let peeked_input: PeekedInput = Box::new(Cursor::new(recorded.take()).chain(rest));
+    options.peeked.put(file, peeked_input);
+
+    lines
+}
+
+fn read_lines<R: BufRead>(mut input: R, count: usize) -> io::Result<Vec<String>> {
+    let mut lines = vec![];
+
+    while lines.len() < count {
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            break;
+        }
+
+        strip_line_ending(&mut line);
+        lines.push(line);
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::open_input;
+
+    #[test]
+    fn test_peek_lines_does_not_consume() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.csv");
+        std::fs::write(&path, "a,b\r\n1,2\n3,4\n").unwrap();
+        let file = path.to_str().unwrap();
+        let options = RtoolOptions::default();
+
+        assert_eq!(vec!["a,b", "1,2"], peek_lines(file, 2, &options).unwrap());
+        assert_eq!(vec!["a,b", "1,2", "3,4"], peek_lines(file, 10, &options).unwrap());
+
+        let mut content = String::new();
+        open_input(file, &options).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("a,b\r\n1,2\n3,4\n", content);
+    }
+
+    #[test]
+    fn test_peeked_inputs_belong_to_options() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("a.csv");
+        std::fs::write(&path, "a,b\n").unwrap();
+        let file = path.to_str().unwrap();
+        let options = RtoolOptions::default();
+
+        assert_eq!(vec!["a,b"], peek_lines(file, 1, &options).unwrap());
+        std::fs::remove_file(&path).unwrap();
+
+        // Other options open the file afresh, the peeked input is left to its options
+        assert!(open_input(file, &RtoolOptions::default()).is_err());
+        let mut content = String::new();
+        open_input(file, &options.clone()).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("a,b\n", content);
+        // The peeked input is handed out once
+        assert!(open_input(file, &options).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_peek_lines_keeps_lines_of_fifo() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("fifo");
+        assert!(std::process::Command::new("mkfifo").arg(&path).status().unwrap().success());
+        let file = path.to_str().unwrap();
+
+        // The FIFO can be read only once: Opening it again would wait for another writer
+        let writer_path = path.clone();
+        let writer = std::thread::spawn(move || std::fs::write(writer_path, "a,b\n1,2\n3,4\n").unwrap());
+
+        let options = RtoolOptions::default();
+        assert_eq!(vec!["a,b"], peek_lines(file, 1, &options).unwrap());
+        let mut content = String::new();
+        open_input(file, &options).unwrap().read_to_string(&mut content).unwrap();
+        assert_eq!("a,b\n1,2\n3,4\n", content);
+
+        writer.join().unwrap();
+    }
+
+    #[test]
+    fn test_tee_reader_records_bytes_read() {
+        let recorded = Rc::new(RefCell::new(vec![]));
+        let tee = TeeReader {
+            inner: Cursor::new("🐣\nrest\n"),
+            recorded: Rc::clone(&recorded),
+        };
+
+        assert_eq!(vec!["🐣"], read_lines(BufReader::new(tee), 1).unwrap());
+        // Bytes buffered ahead of the peeked lines are recorded too
+        assert_eq!(b"\xf0\x9f\x90\xa3\nrest\n".to_vec(), recorded.take());
+    }
+}
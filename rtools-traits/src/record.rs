@@ -0,0 +1,244 @@
+//! Records are lines annotated with their origin: file name, line number and byte offset.
+//!
+
+use std::error::Error;
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str;
+
+use crate::{display_name, RtoolOptions};
+
+/// Where a record comes from
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct RecordContext<'a> {
+    /// Name of the input file, `-` for STDIN
+    pub file: &'a str,
+    /// Line number of the record within its file, starting at 1
+    pub line_number: u64,
+    /// Byte offset of the record within its file, starting at 0
+    pub byte_offset: u64,
+    /// Length of the record in bytes including the line terminator
+    pub byte_len: usize,
+}
+
+/// Rejection of a record by a line processor, located by the file name and line number
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecordError {
+    pub file: String,
+    pub line_number: u64,
+    pub message: String,
+}
+
+impl RecordError {
+    pub fn new(record: &RecordContext, message: impl Into<String>) -> RecordError {
+        RecordError {
+            file: record.file.to_string(),
+            line_number: record.line_number,
+            message: message.into(),
+        }
+    }
+}
+
+impl fmt::Display for RecordError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}: {}", display_name(&self.file), self.line_number, self.message)
+    }
+}
+
+impl Error for RecordError {}
+
+/// Record errors surface from the processing functions as `InvalidData` errors,
+/// the `RecordError` can be recovered with `io::Error::get_ref` and `downcast_ref`
+impl From<RecordError> for io::Error {
+    fn from(err: RecordError) -> io::Error {
+        io::Error::new(io::ErrorKind::InvalidData, err)
+    }
+}
+
+/// Reader of records from a buffered input. Line terminators `\n` and `\r\n` are stripped
+/// from decoded records, raw records keep them
+pub struct RecordReader<'a, R> {
+    input: R,
+    raw: Vec<u8>,
+    record: RecordContext<'a>,
+}
+
+impl<'a, R: BufRead> RecordReader<'a, R> {
+    pub fn new(file: &'a str, input: R) -> RecordReader<'a, R> {
+        RecordReader {
+            input,
+            raw: vec![],
+            record: RecordContext {
+                file,
+                ..Default::default()
+            },
+        }
+    }
+
+    /// Read the next record. Returns `None` at the end of the input.
+    /// Lines that are not valid UTF-8 are `InvalidData` errors
+    pub fn next_record(&mut self) -> io::Result<Option<(&str, &RecordContext<'a>)>> {
+        match self.next_raw_record()? {
+            Some((raw, record)) => Ok(Some((decode_line(raw)?, record))),
+            None => Ok(None),
+        }
+    }
+
+    /// Read the bytes of the next record as they are, including the line terminator.
+    /// Returns `None` at the end of the input
+    pub fn next_raw_record(&mut self) -> io::Result<Option<(&[u8], &RecordContext<'a>)>> {
+        self.record.byte_offset += self.record.byte_len as u64;
+        self.raw.clear();
+
+        let byte_len = self.input.read_until(b'\n', &mut self.raw)?;
+        if byte_len == 0 {
+            self.record.byte_len = 0;
+            return Ok(None);
+        }
+
+        self.record.line_number += 1;
+        self.record.byte_len = byte_len;
+
+        Ok(Some((&self.raw, &self.record)))
+    }
+}
+
+/// Decode the raw record as UTF-8 without its line terminator `\n` or `\r\n`
+pub fn decode_line(raw: &[u8]) -> io::Result<&str> {
+    let line = match raw.strip_suffix(b"\n") {
+        Some(line) => line.strip_suffix(b"\r").unwrap_or(line),
+        None => raw,
+    };
+
+    str::from_utf8(line).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+}
+
+/// Strip the trailing `\n` or `\r\n` off the line
+pub(crate) fn strip_line_ending(line: &mut String) {
+    if line.ends_with('\n') {
+        line.pop();
+        if line.ends_with('\r') {
+            line.pop();
+        }
+    }
+}
+
+/// Prefix of the record with its file name, line number and byte offset as enabled
+/// by the options, e.g. `file.txt:12:345:`
+pub fn record_prefix(record: &RecordContext, options: &RtoolOptions) -> String {
+    let mut prefix = String::new();
+
+    if options.with_filename {
+        prefix.push_str(display_name(record.file));
+        prefix.push(':');
+    }
+
+    if options.line_numbers {
+        prefix.push_str(&format!("{}:", record.line_number));
+    }
+
+    if options.byte_offsets {
+        prefix.push_str(&format!("{}:", record.byte_offset));
+    }
+
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn test_record_reader() {
+        let mut reader = RecordReader::new("a.txt", Cursor::new("ab\r\n🐣\n\nlast"));
+        let mut records = vec![];
+
+        while let Some((line, record)) = reader.next_record().unwrap() {
+            records.push((line.to_string(), *record));
+        }
+
+        let expected = [("ab", 1, 0, 4), ("🐣", 2, 4, 5), ("", 3, 9, 1), ("last", 4, 10, 4)];
+        assert_eq!(expected.len(), records.len());
+        for ((line, record), (exp_line, line_number, byte_offset, byte_len)) in
+            records.iter().zip(expected.iter())
+        {
+            assert_eq!(exp_line, line);
+            assert_eq!(
+                RecordContext {
+                    file: "a.txt",
+                    line_number: *line_number,
+                    byte_offset: *byte_offset,
+                    byte_len: *byte_len,
+                },
+                *record
+            );
+        }
+    }
+
+    #[test]
+    fn test_raw_records() {
+        let mut reader = RecordReader::new("a.txt", Cursor::new(b"a\xffb\r\nlast".to_vec()));
+
+        let (raw, record) = reader.next_raw_record().unwrap().unwrap();
+        assert_eq!((&b"a\xffb\r\n"[..], 1, 5), (raw, record.line_number, record.byte_len));
+        assert_eq!(io::ErrorKind::InvalidData, decode_line(raw).err().unwrap().kind());
+
+        let (raw, record) = reader.next_raw_record().unwrap().unwrap();
+        assert_eq!(("last", 2, 5), (decode_line(raw).unwrap(), record.line_number, record.byte_offset));
+        assert!(reader.next_raw_record().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_record_error() {
+        let record = RecordContext {
+            file: "-",
+            line_number: 3,
+            ..Default::default()
+        };
+        let err: io::Error = RecordError::new(&record, "expected 2 fields, found 1").into();
+
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+        assert_eq!("(standard input):3: expected 2 fields, found 1", err.to_string());
+        assert_eq!(
+            Some(3),
+            err.get_ref()
+                .and_then(|err| err.downcast_ref::<RecordError>())
+                .map(|err| err.line_number)
+        );
+    }
+
+    #[test]
+    fn test_record_prefix() {
+        let record = RecordContext {
+            file: "-",
+            line_number: 12,
+            byte_offset: 345,
+            byte_len: 6,
+        };
+
+        assert_eq!("", record_prefix(&record, &RtoolOptions::default()));
+        assert_eq!(
+            "(standard input):12:345:",
+            record_prefix(
+                &record,
+                &RtoolOptions {
+                    with_filename: true,
+                    line_numbers: true,
+                    byte_offsets: true,
+                    ..Default::default()
+                }
+            )
+        );
+        assert_eq!(
+            "345:",
+            record_prefix(
+                &record,
+                &RtoolOptions {
+                    byte_offsets: true,
+                    ..Default::default()
+                }
+            )
+        );
+    }
+}
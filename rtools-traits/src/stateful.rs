@@ -0,0 +1,192 @@
+//! Stateful processors for aggregating tools like `wc`, `uniq` or `tail`:
+//! State is mutated line by line and results may be written only once a file or all files end.
+//!
+
+use std::io::{self, BufRead};
+use std::sync::Mutex;
+
+use crate::{decode_line, LineProcessorT, RecordContext};
+
+/// Processor of lines with mutable state. The hooks are called in order:
+/// `begin_file`, `process_line` for each record, `end_file` for each input, then `finish` once
+pub trait StatefulProcessorT<C> {
+    /// Output before the records of the input file. Nothing by default
+    fn begin_file(&mut self, _file: &str, _context: &C) -> Vec<u8> {
+        vec![]
+    }
+
+    fn process_line(&mut self, line: &str, record: &RecordContext, context: &C) -> Vec<u8>;
+
+    /// Process the whole input at once instead of line by line, e.g. to scan it in chunks.
+    /// `None` by default: the lines are processed
+    fn process_whole_input(&mut self, _input: &mut dyn BufRead, _context: &C) -> Option<io::Result<Vec<u8>>> {
+        None
+    }
+
+    /// Process the raw bytes of the line, including the line terminator.
+    /// By default, the line is decoded as UTF-8 and processed by `process_line`
+    fn process_raw_line(&mut self, raw: &[u8], record: &RecordContext, context: &C) -> io::Result<Vec<u8>> {
+        Ok(self.process_line(decode_line(raw)?, record, context))
+    }
+
+    /// Output after the records of the input file. Nothing by default
+    fn end_file(&mut self, _file: &str, _context: &C) -> Vec<u8> {
+        vec![]
+    }
+
+    /// Output after all input files. Nothing by default
+    fn finish(&mut self, _context: &C) -> Vec<u8> {
+        vec![]
+    }
+}
+
+/// Line processor driving a stateful processor, so that `RtoolT` can process files with it
+pub struct StatefulLineProcessor<S> {
+    processor: Mutex<S>,
+}
+
+impl<S> StatefulLineProcessor<S> {
+    pub fn new(processor: S) -> StatefulLineProcessor<S> {
+        StatefulLineProcessor {
+            processor: Mutex::new(processor),
+        }
+    }
+
+    /// The stateful processor with the state left by the processed files
+    pub fn into_inner(self) -> S {
+        self.processor.into_inner().unwrap()
+    }
+}
+
+impl<C, S: StatefulProcessorT<C>> LineProcessorT<C> for StatefulLineProcessor<S> {
+    fn process(&self, line: &str, context: &C) -> Vec<u8> {
+        self.process_record(line, &RecordContext::default(), context)
+    }
+
+    fn process_record(&self, line: &str, record: &RecordContext, context: &C) -> Vec<u8> {
+        self.processor.lock().unwrap().process_line(line, record, context)
+    }
+
+    fn try_process_raw_record(&self, raw: &[u8], record: &RecordContext, context: &C) -> io::Result<Vec<u8>> {
+        self.processor.lock().unwrap().process_raw_line(raw, record, context)
+    }
+
+    fn try_process_whole_input(&self, input: &mut dyn BufRead, context: &C) -> Option<io::Result<Vec<u8>>> {
+        self.processor.lock().unwrap().process_whole_input(input, context)
+    }
+
+    fn begin_file(&self, file: &str, context: &C) -> Vec<u8> {
+        self.processor.lock().unwrap().begin_file(file, context)
+    }
+
+    fn end_file(&self, file: &str, context: &C) -> Vec<u8> {
+        self.processor.lock().unwrap().end_file(file, context)
+    }
+
+    fn finish(&self, context: &C) -> Vec<u8> {
+        self.processor.lock().unwrap().finish(context)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{RtoolOptions, RtoolT};
+    use std::io::{BufWriter, Cursor, Write};
+
+    /// Counter of the lines per file, like `wc -l`
+    #[derive(Default)]
+    struct LineCounter {
+        lines: u64,
+        total: u64,
+    }
+
+    impl StatefulProcessorT<()> for LineCounter {
+        fn process_line(&mut self, _line: &str, _record: &RecordContext, _context: &()) -> Vec<u8> {
+            self.lines += 1;
+            vec![]
+        }
+
+        fn end_file(&mut self, file: &str, _context: &()) -> Vec<u8> {
+            self.total += self.lines;
+            let out = format!("{} {}\n", self.lines, file).into_bytes();
+            self.lines = 0;
+            out
+        }
+
+        fn finish(&mut self, _context: &()) -> Vec<u8> {
+            format!("{} total\n", self.total).into_bytes()
+        }
+    }
+
+    /// Counter of the bytes of all files, like `wc -c`
+    #[derive(Default)]
+    struct ByteCounter {
+        bytes: usize,
+    }
+
+    impl StatefulProcessorT<()> for ByteCounter {
+        fn process_line(&mut self, _line: &str, _record: &RecordContext, _context: &()) -> Vec<u8> {
+            unreachable!("lines are counted from their raw bytes")
+        }
+
+        fn process_raw_line(&mut self, raw: &[u8], _record: &RecordContext, _context: &()) -> io::Result<Vec<u8>> {
+            self.bytes += raw.len();
+            Ok(vec![])
+        }
+
+        fn finish(&mut self, _context: &()) -> Vec<u8> {
+            format!("{}\n", self.bytes).into_bytes()
+        }
+    }
+
+    struct CountingTool {}
+
+    impl<P: LineProcessorT<()>> RtoolT<(), P> for CountingTool {}
+
+    /// Process the inputs one after the other, then finish, as `process_files_with_options` does
+    fn process_inputs<P: LineProcessorT<()>>(line_processor: &P, inputs: &[(&str, &[u8])]) -> io::Result<String> {
+        let mut output = BufWriter::new(vec![]);
+        for (file, content) in inputs {
+            CountingTool {}.process_input(line_processor, file, Cursor::new(content), &mut output, &(), &RtoolOptions::default())?;
+        }
+        output.write_all(&line_processor.finish(&()))?;
+        Ok(String::from_utf8(output.into_inner()?).unwrap())
+    }
+
+    #[test]
+    fn test_process_inputs_with_stateful_processor() {
+        let line_processor = StatefulLineProcessor::new(LineCounter::default());
+        assert_eq!(
+            "3 a\n0 b\n3 total\n",
+            process_inputs(&line_processor, &[("a", b"a1\na2\r\na3"), ("b", b"")]).unwrap()
+        );
+        assert_eq!(3, line_processor.into_inner().total);
+    }
+
+    #[test]
+    fn test_process_lines_leaves_finish_to_the_caller() {
+        let line_processor = StatefulLineProcessor::new(LineCounter::default());
+        let mut output = BufWriter::new(vec![]);
+
+        CountingTool {}.process_lines(&line_processor, Cursor::new("a1\na2\n"), &mut output, &());
+        CountingTool {}.process_lines(&line_processor, Cursor::new("b1\n"), &mut output, &());
+        output.write_all(&line_processor.finish(&())).unwrap();
+        assert_eq!("2 \n1 \n3 total\n", String::from_utf8(output.into_inner().unwrap()).unwrap());
+    }
+
+    #[test]
+    fn test_process_raw_lines() {
+        let inputs: &[(&str, &[u8])] = &[("latin1", b"caf\xe9\r\nok\n")];
+        assert_eq!(
+            "9\n",
+            process_inputs(&StatefulLineProcessor::new(ByteCounter::default()), inputs).unwrap()
+        );
+
+        // Lines are decoded for processors of decoded lines
+        let err = process_inputs(&StatefulLineProcessor::new(LineCounter::default()), inputs)
+            .err()
+            .unwrap();
+        assert_eq!(io::ErrorKind::InvalidData, err.kind());
+    }
+}
@@ -0,0 +1,408 @@
+//! `rwc` is a Rust replacement for GNU wc that supports UTF-8.
+//! Implementation details are exported for reusability in case users
+//! are interested in building their own counters.
+//!
+
+use std::io::{self, BufRead};
+
+extern crate rtools_traits;
+use rtools_traits::{LineProcessorT, RecordContext, RtoolT, StatefulProcessorT};
+
+use unicode_width::UnicodeWidthChar;
+
+/// Cargo version specified in the Cargo.toml file
+const VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Cargo version specified in the Cargo.toml file
+pub fn version() -> &'static str {
+    VERSION
+}
+
+/// Tab stops used to measure the width of lines, like GNU wc
+const TAB_WIDTH: usize = 8;
+
+/// Counts to compute and print
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CountSelection {
+    pub lines: bool,
+    pub words: bool,
+    pub chars: bool,
+    pub bytes: bool,
+    pub max_line_width: bool,
+}
+
+impl CountSelection {
+    /// Lines, words and bytes, like GNU wc without flags
+    pub fn default_counts() -> CountSelection {
+        CountSelection {
+            lines: true,
+            words: true,
+            bytes: true,
+            ..Default::default()
+        }
+    }
+
+    /// Whether lines have to be decoded, rather than only scanned for newlines
+    fn needs_text(&self) -> bool {
+        self.words || self.chars || self.max_line_width
+    }
+}
+
+/// Counts of an input
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Number of newlines
+    pub lines: u64,
+    /// Number of sequences of non-whitespace chars
+    pub words: u64,
+    /// Number of UTF-8 chars, invalid bytes are not counted
+    pub chars: u64,
+    pub bytes: u64,
+    /// Display width of the widest line
+    pub max_line_width: u64,
+}
+
+impl Counts {
+    /// Account for the line including its newline, if any. Words, chars and the width
+    /// are counted in one pass, invalid UTF-8 sequences are part of words but not chars or width
+    pub fn add_line(&mut self, line: &[u8]) {
+        self.bytes += line.len() as u64;
+        if line.ends_with(b"\n") {
+            self.lines += 1;
+        }
+
+        let mut in_word = false;
+        let mut width = 0;
+        // Invalid sequences are `None`: they belong to words like GNU wc, without width
+        let mut scan = |c: Option<char>| {
+            if c.is_some_and(char::is_whitespace) {
+                in_word = false;
+            } else if !in_word {
+                in_word = true;
+                self.words += 1;
+            }
+            if let Some(c) = c {
+                width = advance_width(width, c);
+            }
+        };
+
+        let mut chars = 0;
+        for chunk in line.utf8_chunks() {
+            for c in chunk.valid().chars() {
+                chars += 1;
+                scan(Some(c));
+            }
+            if !chunk.invalid().is_empty() {
+                scan(None);
+            }
+        }
+
+        self.chars += chars;
+        self.max_line_width = self.max_line_width.max(width as u64);
+    }
+
+    /// Add the counts of another input, e.g. to compute the total
+    pub fn merge(&mut self, other: &Counts) {
+        self.lines += other.lines;
+        self.words += other.words;
+        self.chars += other.chars;
+        self.bytes += other.bytes;
+        self.max_line_width = self.max_line_width.max(other.max_line_width);
+    }
+
+    /// Selected counts in the order of GNU wc: lines, words, chars, bytes and max line width
+    pub fn values(&self, selection: &CountSelection) -> Vec<u64> {
+        [
+            (selection.lines, self.lines),
+            (selection.words, self.words),
+            (selection.chars, self.chars),
+            (selection.bytes, self.bytes),
+            (selection.max_line_width, self.max_line_width),
+        ]
+        .iter()
+        .filter(|(selected, _)| *selected)
+        .map(|(_, value)| *value)
+        .collect()
+    }
+}
+
+/// Display width of the line: East Asian wide chars take 2 columns, tabs advance
+/// to the next tab stop, control chars and the line terminator take none
+pub fn display_width(line: &str) -> usize {
+    line.chars().fold(0, advance_width)
+}
+
+/// Width of the line so far after the char
+fn advance_width(width: usize, c: char) -> usize {
+    match c {
+        '\t' => (width / TAB_WIDTH + 1) * TAB_WIDTH,
+        c => width + UnicodeWidthChar::width(c).unwrap_or(0),
+    }
+}
+
+/// Count the input. Only newlines are scanned for, unless words, chars or widths are selected
+pub fn count_input<R: BufRead>(mut input: R, selection: &CountSelection) -> io::Result<Counts> {
+    let mut counts = Counts::default();
+
+    if !selection.needs_text() {
+        loop {
+            let buffer = input.fill_buf()?;
+            if buffer.is_empty() {
+                break;
+            }
+
+            counts.bytes += buffer.len() as u64;
+            counts.lines += buffer.iter().filter(|byte| **byte == b'\n').count() as u64;
+
+            let len = buffer.len();
+            input.consume(len);
+        }
+
+        return Ok(counts);
+    }
+
+    let mut line = vec![];
+    while input.read_until(b'\n', &mut line)? > 0 {
+        counts.add_line(&line);
+        line.clear();
+    }
+
+    Ok(counts)
+}
+
+/// Counter of the inputs processed, like GNU wc: Each input gets a row of counts,
+/// followed by their total on demand. The rows are written once all inputs are counted
+#[derive(Debug, Default)]
+pub struct WcCounter {
+    counts: Counts,
+    rows: Vec<(Counts, Option<String>)>,
+    /// Whether rows are followed by the names of the inputs
+    named: bool,
+    with_total: bool,
+}
+
+impl WcCounter {
+    pub fn new(named: bool, with_total: bool) -> WcCounter {
+        WcCounter {
+            named,
+            with_total,
+            ..Default::default()
+        }
+    }
+
+}
+
+impl StatefulProcessorT<CountSelection> for WcCounter {
+    fn begin_file(&mut self, _file: &str, _selection: &CountSelection) -> Vec<u8> {
+        self.counts = Counts::default();
+        vec![]
+    }
+
+    fn process_line(&mut self, _line: &str, _record: &RecordContext, _selection: &CountSelection) -> Vec<u8> {
+        unreachable!("lines are counted from their raw bytes, including invalid UTF-8 and terminators")
+    }
+
+    /// Whole inputs are counted by `count_input`
+    fn process_whole_input(&mut self, input: &mut dyn BufRead, selection: &CountSelection) -> Option<io::Result<Vec<u8>>> {
+        Some(count_input(input, selection).map(|counts| {
+            self.counts = counts;
+            vec![]
+        }))
+    }
+
+    /// Lines are counted one by one when records are filtered
+    fn process_raw_line(
+        &mut self,
+        raw: &[u8],
+        _record: &RecordContext,
+        _selection: &CountSelection,
+    ) -> io::Result<Vec<u8>> {
+        self.counts.add_line(raw);
+        Ok(vec![])
+    }
+
+    fn end_file(&mut self, file: &str, _selection: &CountSelection) -> Vec<u8> {
+        let name = if self.named { Some(file.to_string()) } else { None };
+        self.rows.push((self.counts, name));
+        vec![]
+    }
+
+    fn finish(&mut self, selection: &CountSelection) -> Vec<u8> {
+        let mut rows: Vec<(Counts, Option<&str>)> = self
+            .rows
+            .iter()
+            .map(|(counts, name)| (*counts, name.as_deref()))
+            .collect();
+
+        if self.with_total {
+            let mut total = Counts::default();
+            for (counts, _) in &rows {
+                total.merge(counts);
+            }
+            rows.push((total, Some("total")));
+        }
+
+        format_rows(&rows, selection).into_bytes()
+    }
+}
+
+/// Driver of `WcCounter` over files, the file `-` stands for STDIN.
+/// Unreadable files are skipped and returned as `rtools_traits::UnreadableFiles`
+pub struct WcProcessor {}
+
+impl<P: LineProcessorT<CountSelection>> RtoolT<CountSelection, P> for WcProcessor {}
+
+/// Format rows of selected counts like GNU wc: Counts are right-aligned
+/// to the width of the largest count and followed by the name, if any
+pub fn format_rows(rows: &[(Counts, Option<&str>)], selection: &CountSelection) -> String {
+    let width = rows
+        .iter()
+        .flat_map(|(counts, _)| counts.values(selection))
+        .max()
+        .map_or(1, |value| value.to_string().len());
+
+    let mut out = String::new();
+    for (counts, name) in rows {
+        let values: Vec<String> = counts
+            .values(selection)
+            .iter()
+            .map(|value| format!("{:>width$}", value, width = width))
+            .collect();
+
+        out.push_str(&values.join(" "));
+        if let Some(name) = name {
+            out.push(' ');
+            out.push_str(name);
+        }
+        out.push('\n');
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rtools_traits::{RtoolOptions, StatefulLineProcessor};
+    use std::io::{BufWriter, Cursor, Write};
+
+    const _ALL: CountSelection = CountSelection {
+        lines: true,
+        words: true,
+        chars: true,
+        bytes: true,
+        max_line_width: true,
+    };
+
+    #[test]
+    fn test_display_width() {
+        assert_eq!(0, display_width(""));
+        assert_eq!(6, display_width("Hà Nội"));
+        assert_eq!(10, display_width("東京タワー"));
+        assert_eq!(9, display_width("ab\tc"));
+        assert_eq!(17, display_width("12345678\tx\u{7}"));
+    }
+
+    #[test]
+    fn test_count_input() {
+        let input = "🐣 hello  world\n\tx\r\nlast line";
+        assert_eq!(
+            Counts {
+                lines: 2,
+                words: 6,
+                chars: 28,
+                bytes: 31,
+                max_line_width: 15,
+            },
+            count_input(Cursor::new(input), &_ALL).unwrap()
+        );
+
+        // Lines and bytes only are counted without decoding
+        let selection = CountSelection {
+            lines: true,
+            bytes: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            Counts {
+                lines: 2,
+                bytes: 31,
+                ..Default::default()
+            },
+            count_input(Cursor::new(input), &selection).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_count_invalid_utf8() {
+        let counts = count_input(Cursor::new(b"a\xffb c\n".to_vec()), &_ALL).unwrap();
+        assert_eq!(
+            (1, 2, 5, 6, 4),
+            (counts.lines, counts.words, counts.chars, counts.bytes, counts.max_line_width)
+        );
+    }
+
+    #[test]
+    fn test_count_inputs() {
+        let line_processor = StatefulLineProcessor::new(WcCounter::new(true, true));
+        let inputs: &[(&str, &[u8])] = &[("a", b"a\xffb c\r\n\xe6\x9d\xb1\xe4\xba\xac\n"), ("b", b"x\ty")];
+
+        let mut output = BufWriter::new(vec![]);
+        for (file, content) in inputs {
+            WcProcessor {}
+                .process_input(&line_processor, file, Cursor::new(content), &mut output, &_ALL, &RtoolOptions::default())
+                .unwrap();
+        }
+        output.write_all(&line_processor.finish(&_ALL)).unwrap();
+
+        // Lines that are not valid UTF-8 are counted too
+        assert_eq!(
+            " 2  3  9 14  4 a\n 0  2  3  3  9 b\n 2  5 12 17  9 total\n",
+            String::from_utf8(output.into_inner().unwrap()).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_count_files_skips_unreadable_files() {
+        let mut out = vec![];
+        let err = WcProcessor {}
+            .process_files_with_options(
+                &StatefulLineProcessor::new(WcCounter::new(true, true)),
+                &vec!["missing.txt"],
+                &mut out,
+                &_ALL,
+                &RtoolOptions::default(),
+            )
+            .err()
+            .unwrap();
+        assert_eq!("0 0 0 0 0 total\n", String::from_utf8(out).unwrap());
+        assert_eq!(io::ErrorKind::NotFound, err.kind());
+        assert!(err.to_string().starts_with("missing.txt: "));
+    }
+
+    #[test]
+    fn test_format_rows() {
+        let a = Counts {
+            lines: 3,
+            words: 10,
+            bytes: 60,
+            ..Default::default()
+        };
+        let mut total = a;
+        total.merge(&a);
+
+        assert_eq!(
+            "  3  10  60 a.txt\n  3  10  60 a.txt\n  6  20 120 total\n",
+            format_rows(
+                &[(a, Some("a.txt")), (a, Some("a.txt")), (total, Some("total"))],
+                &CountSelection::default_counts()
+            )
+        );
+
+        let selection = CountSelection {
+            lines: true,
+            ..Default::default()
+        };
+        assert_eq!("3\n", format_rows(&[(a, None)], &selection));
+    }
+}
@@ -0,0 +1,144 @@
+extern crate clap;
+use clap::{App, Arg};
+
+extern crate rtools_traits;
+use rtools_traits::{RtoolOptions, RtoolT, StatefulLineProcessor, UnreadableFiles};
+
+extern crate rwc_lib;
+use rwc_lib::{version, CountSelection, WcCounter, WcProcessor};
+
+/// Perform operations similar to GNU wc
+pub fn do_rwc(input_args: &Vec<&str>) {
+    const _STR_LINES: &str = "lines";
+    const _STR_WORDS: &str = "words";
+    const _STR_CHARS: &str = "chars";
+    const _STR_BYTES: &str = "bytes";
+    const _STR_MAX_LINE_LENGTH: &str = "max-line-length";
+    const _STR_DECOMPRESS: &str = "decompress";
+
+    let matches = App::new("rwc")
+        .version(version())
+        .about("Replacement for GNU wc. Written in Rust and supports UTF-8.")
+        .author("Viet Le")
+        .arg(
+            Arg::with_name(_STR_LINES)
+                .short("l")
+                .long(_STR_LINES)
+                .help("Print the newline counts.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_WORDS)
+                .short("w")
+                .long(_STR_WORDS)
+                .help("Print the word counts. Words are separated by whitespace.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_CHARS)
+                .short("m")
+                .long(_STR_CHARS)
+                .help("Print the UTF-8 character counts.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_BYTES)
+                .short("c")
+                .long(_STR_BYTES)
+                .help("Print the byte counts.")
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_MAX_LINE_LENGTH)
+                .short("L")
+                .long(_STR_MAX_LINE_LENGTH)
+                .help(
+                    "Print the maximum display width of lines.\n\
+                    Wide chars take 2 columns, tabs stop every 8 columns.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name(_STR_DECOMPRESS)
+                .long(_STR_DECOMPRESS)
+                .help(
+                    "Decompress inputs compressed with gzip/zstd/xz/bzip2.\n\
+                    Compression is auto-detected by magic bytes.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("files")
+                .help(
+                    "The content of these files will be used.\n\
+                     If no files given, STDIN will be used.\n\
+                     The file - stands for STDIN.",
+                )
+                .next_line_help(true)
+                .required(false)
+                .multiple(true),
+        )
+        .get_matches_from(input_args);
+
+    let selection = CountSelection {
+        lines: matches.is_present(_STR_LINES),
+        words: matches.is_present(_STR_WORDS),
+        chars: matches.is_present(_STR_CHARS),
+        bytes: matches.is_present(_STR_BYTES),
+        max_line_width: matches.is_present(_STR_MAX_LINE_LENGTH),
+    };
+    let selection = if selection == CountSelection::default() {
+        CountSelection::default_counts()
+    } else {
+        selection
+    };
+
+    let options = RtoolOptions {
+        decompress: matches.is_present(_STR_DECOMPRESS),
+        // Bytes and chars of BOMs are counted, like GNU wc
+        keep_bom: true,
+        ..Default::default()
+    };
+
+    // NOTE: Use `values_of` instead of `value_of`!!!!
+    let files: Vec<&str> = match matches.values_of("files") {
+        Some(files_it) => files_it.collect(),
+        None => vec![],
+    };
+
+    // STDIN is not named unless given as -, the total follows many files
+    let counter = StatefulLineProcessor::new(WcCounter::new(!files.is_empty(), files.len() > 1));
+
+    let result = WcProcessor {}.process_to_stdout_with_options(&counter, &files, &selection, &options);
+    if let Err(err) = result {
+        // Unreadable files are reported like GNU wc, after the counts of the other files
+        match err.get_ref().and_then(|err| err.downcast_ref::<UnreadableFiles>()) {
+            Some(unreadable_files) => {
+                for line in unreadable_files.to_string().lines() {
+                    eprintln!("rwc: {}", line);
+                }
+            }
+            None => eprintln!("Could not count the files. The error: {}", err),
+        }
+        std::process::exit(1);
+    }
+}
+
+fn main() {
+    let input_args: Vec<_> = std::env::args().collect();
+    let input_args = input_args.iter().map(|s| s.as_str()).collect();
+
+    do_rwc(&input_args)
+}
@@ -0,0 +1,38 @@
+//! Tests of `rwc` run as a process with piped STDIN.
+//!
+
+use std::io::Write;
+use std::process::{Command, Output, Stdio};
+
+/// Run rwc with the arguments and STDIN
+fn rwc(args: &[&str], stdin: &str) -> Output {
+    let mut child = Command::new(env!("CARGO_BIN_EXE_rwc"))
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    child.stdin.take().unwrap().write_all(stdin.as_bytes()).unwrap();
+    child.wait_with_output().unwrap()
+}
+
+#[test]
+fn test_count_stdin() {
+    let output = rwc(&["-l", "-w", "-c"], "a b\n🐣\n");
+    assert!(output.status.success());
+    assert_eq!("2 3 9\n", String::from_utf8(output.stdout).unwrap());
+}
+
+#[cfg(unix)]
+#[test]
+fn test_count_with_missing_file() {
+    let output = rwc(&["-l", "-", "missing.txt"], "a\nb\n");
+    assert!(!output.status.success());
+    // The other files and the total are counted nonetheless
+    assert_eq!("2 -\n2 total\n", String::from_utf8(output.stdout).unwrap());
+    assert_eq!(
+        "rwc: missing.txt: No such file or directory\n",
+        String::from_utf8(output.stderr).unwrap()
+    );
+}